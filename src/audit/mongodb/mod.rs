@@ -1,5 +1,4 @@
 mod record;
 mod reporter;
 
-pub use self::reporter::Reporter;
 pub use self::reporter::ReporterFactory;
@@ -19,13 +19,19 @@ use crate::models::RequestProtocol;
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AuditRecord {
     pub authenticated: bool,
+    pub authenticator: Option<String>,
+
+    /// Client IP address as a string.
+    pub client_ip: Option<String>,
 
     /// Duration in a BSON compatible format.
     pub duration: SignedDuration,
 
+    pub method: Option<String>,
     pub protocol: RequestProtocol,
     pub reason: AuditReason,
     pub resource: String,
+    pub roles: Vec<String>,
     pub result: AuthenticationStatus,
     pub session_id: Option<String>,
 
@@ -39,10 +45,14 @@ impl From<NativeAuditRecord> for AuditRecord {
     fn from(native: NativeAuditRecord) -> AuditRecord {
         AuditRecord {
             authenticated: native.authenticated,
+            authenticator: native.authenticator,
+            client_ip: native.client_ip.map(|ip| ip.to_string()),
             duration: native.duration.into(),
+            method: native.method,
             protocol: native.protocol,
             reason: native.reason,
             resource: native.resource,
+            roles: native.roles,
             result: native.result,
             session_id: native.session_id,
             timestamp: native.timestamp.into(),
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use actix_web::HttpRequest;
+use anyhow::Result;
+
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::ApiKeyConfig;
+use crate::config::ApiKeyEntry;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// Length of hex encoded SHA3-512 digests.
+const DIGEST_HEX_LEN: usize = 128;
+
+/// Authenticate requests with static API keys (application passwords).
+pub struct ApiKey {
+    header: String,
+    keys: Arc<HashMap<String, ApiKeyEntry>>,
+}
+
+impl ApiKey {
+    /// Attempt to extract the API key from the request.
+    fn extract_key<'request>(&self, request: &'request HttpRequest) -> Option<&'request str> {
+        super::credentials::extract_bearer(request).or_else(|| {
+            request
+                .headers()
+                .get(self.header.as_str())
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+        })
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for ApiKey {
+    async fn check(
+        &self,
+        _: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let key = match self.extract_key(request) {
+            None => {
+                return Ok(AuthenticationResult::from_status(
+                    AuthenticationStatus::MustLogin,
+                ))
+            }
+            Some(key) => key,
+        };
+
+        // Look up the key by digest so plain text keys are never stored.
+        let hash = crate::authenticator::hash_token(key);
+        let entry = match self.keys.get(&hash) {
+            None => {
+                log::debug!("Request presented an unknown API key");
+                return Ok(AuthenticationResult::from_status(
+                    AuthenticationStatus::MustLogin,
+                ));
+            }
+            Some(entry) => entry,
+        };
+
+        // Return the identity attached to the API key.
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        result.authentication_context.authenticated = true;
+        result.authentication_context.attributes = entry.attributes.clone();
+        result.authentication_context.user = Some(entry.user.clone());
+        result.authentication_context.session = Some(hash);
+        Ok(result)
+    }
+}
+
+/// Instantiate per-thread `ApiKey` instances sharing the same keys index.
+pub struct ApiKeyFactory {
+    header: String,
+    keys: Arc<HashMap<String, ApiKeyEntry>>,
+}
+
+impl ApiKeyFactory {
+    pub fn from_config(config: &ApiKeyConfig) -> Result<ApiKeyFactory> {
+        let mut keys = HashMap::new();
+        for entry in &config.keys {
+            let hash = entry.hash.to_uppercase();
+            let valid = hash.chars().all(|c| c.is_ascii_hexdigit());
+            if !valid || hash.len() != DIGEST_HEX_LEN {
+                anyhow::bail!(
+                    "API key hash for user '{}' is not a hex encoded SHA3-512 digest",
+                    entry.user
+                );
+            }
+            if keys.insert(hash, entry.clone()).is_some() {
+                anyhow::bail!("API key for user '{}' is configured twice", entry.user);
+            }
+        }
+        Ok(ApiKeyFactory {
+            header: config.header.to_lowercase(),
+            keys: Arc::new(keys),
+        })
+    }
+}
+
+impl AuthenticationProxyFactory for ApiKeyFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        Box::new(ApiKey {
+            header: self.header.clone(),
+            keys: Arc::clone(&self.keys),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::ApiKeyFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::ApiKeyConfig;
+    use crate::config::ApiKeyEntry;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    fn factory() -> ApiKeyFactory {
+        let config = ApiKeyConfig {
+            header: "X-Api-Key".into(),
+            keys: vec![ApiKeyEntry {
+                attributes: {
+                    let mut map = std::collections::HashMap::new();
+                    map.insert("team".to_string(), "ci".to_string());
+                    map
+                },
+                hash: crate::authenticator::hash_token("s3cr3t").to_lowercase(),
+                user: "ci-bot".into(),
+            }],
+        };
+        ApiKeyFactory::from_config(&config).unwrap()
+    }
+
+    fn test_request() -> TestRequest {
+        TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"))
+            .uri("/v1/check")
+    }
+
+    #[actix_rt::test]
+    async fn allow_bearer_token() {
+        let request = test_request()
+            .append_header(("Authorization", "Bearer s3cr3t"))
+            .to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        let result = factory().make().check(&context, &request).await.unwrap();
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert!(result.authentication_context.authenticated);
+        assert_eq!(result.authentication_context.user, Some("ci-bot".into()));
+        assert_eq!(
+            result.authentication_context.attributes.get("team"),
+            Some(&"ci".to_string())
+        );
+        assert_eq!(
+            result.authentication_context.session,
+            Some(crate::authenticator::hash_token("s3cr3t"))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn allow_key_header() {
+        let request = test_request()
+            .append_header(("X-Api-Key", "s3cr3t"))
+            .to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        let result = factory().make().check(&context, &request).await.unwrap();
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authentication_context.user, Some("ci-bot".into()));
+    }
+
+    #[actix_rt::test]
+    async fn must_login_without_key() {
+        let request = test_request().to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        let result = factory().make().check(&context, &request).await.unwrap();
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert_eq!(result.authentication_context.user, None);
+    }
+
+    #[actix_rt::test]
+    async fn must_login_with_unknown_key() {
+        let request = test_request()
+            .append_header(("Authorization", "Bearer wrong"))
+            .to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        let result = factory().make().check(&context, &request).await.unwrap();
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert!(!result.authentication_context.authenticated);
+    }
+
+    #[test]
+    fn reject_invalid_hash() {
+        let config = ApiKeyConfig {
+            header: "X-Api-Key".into(),
+            keys: vec![ApiKeyEntry {
+                attributes: Default::default(),
+                hash: "not-a-digest".into(),
+                user: "ci-bot".into(),
+            }],
+        };
+        assert!(ApiKeyFactory::from_config(&config).is_err());
+    }
+}
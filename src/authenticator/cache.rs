@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Maximum number of values kept in a cache, to bound memory use.
+const MAX_ENTRIES: usize = 10_000;
+
+/// Cached values and their keys in insertion (and so expiry) order.
+struct Entries<V> {
+    order: VecDeque<(Instant, String)>,
+    values: HashMap<String, (Instant, V)>,
+}
+
+/// Thread-safe cache of values that expire a fixed time after they are inserted.
+///
+/// Values are dropped in insertion order when new values are inserted, once they expire
+/// or to make room when the cache is full, so the cache does not grow forever.
+pub struct TtlCache<V> {
+    entries: Mutex<Entries<V>>,
+    max_entries: usize,
+    ttl: Duration,
+}
+
+impl<V: Clone> TtlCache<V> {
+    /// Create an empty cache that keeps values for the given TTL.
+    pub fn new(ttl: Duration) -> TtlCache<V> {
+        let entries = Entries {
+            order: VecDeque::new(),
+            values: HashMap::new(),
+        };
+        TtlCache {
+            entries: Mutex::new(entries),
+            max_entries: MAX_ENTRIES,
+            ttl,
+        }
+    }
+
+    /// Return a copy of the value for the key, unless it has expired.
+    pub fn get(&self, key: &str) -> Option<V> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+        let entries = self.entries.lock().ok()?;
+        match entries.values.get(key) {
+            Some((expires, value)) if *expires > Instant::now() => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Insert or replace the value for the key.
+    pub fn insert(&self, key: String, value: V) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => {
+                log::error!("Cache lock is poisoned, value not cached");
+                return;
+            }
+        };
+        let entries = &mut *entries;
+        let now = Instant::now();
+
+        // Drop the oldest values while they are expired or the cache is full.
+        while let Some((expires, _)) = entries.order.front() {
+            if *expires > now && entries.values.len() < self.max_entries {
+                break;
+            }
+            let (expires, old_key) = entries.order.pop_front().unwrap();
+            // Skip keys that were replaced after this value was inserted.
+            let current = entries.values.get(&old_key).map(|(current, _)| *current);
+            if current == Some(expires) {
+                entries.values.remove(&old_key);
+            }
+        }
+
+        let expires = now + self.ttl;
+        entries.order.push_back((expires, key.clone()));
+        entries.values.insert(key, (expires, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TtlCache;
+
+    #[test]
+    fn disabled_with_zero_ttl() {
+        let cache = TtlCache::new(Duration::from_secs(0));
+        cache.insert("key".into(), 42);
+        assert_eq!(cache.get("key"), None);
+    }
+
+    #[test]
+    fn expired_values_are_dropped() {
+        let cache = TtlCache::new(Duration::from_millis(10));
+        cache.insert("old".into(), 1);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get("old"), None);
+        cache.insert("new".into(), 2);
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.values.len(), 1);
+        assert_eq!(entries.order.len(), 1);
+    }
+
+    #[test]
+    fn full_cache_drops_oldest_values() {
+        let mut cache = TtlCache::new(Duration::from_secs(60));
+        cache.max_entries = 2;
+        cache.insert("first".into(), 1);
+        cache.insert("second".into(), 2);
+        cache.insert("first".into(), 3);
+        cache.insert("third".into(), 4);
+        assert_eq!(cache.get("first"), Some(3));
+        assert_eq!(cache.get("second"), None);
+        assert_eq!(cache.get("third"), Some(4));
+        assert_eq!(cache.entries.lock().unwrap().values.len(), 2);
+    }
+
+    #[test]
+    fn get_inserted_value() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        cache.insert("key".into(), 42);
+        assert_eq!(cache.get("key"), Some(42));
+        assert_eq!(cache.get("other"), None);
+    }
+}
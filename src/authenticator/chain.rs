@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use actix_web::HttpRequest;
+use anyhow::Result;
+
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// Named authentication proxy in a chain.
+struct ChainLink {
+    name: String,
+    proxy: Box<dyn AuthenticationProxy>,
+}
+
+/// Check requests against a list of authentication proxies in order.
+///
+/// The first proxy to allow the request wins.
+/// If no proxy allows the request, the request is denied if any proxy denied it
+/// and users are asked to login only if all proxies asked for it.
+/// Proxies that fail are skipped and their error is returned only if no proxy returned a result.
+pub struct Chain {
+    links: Vec<ChainLink>,
+}
+
+impl Chain {
+    /// Create a chain from a list of named authentication proxies.
+    #[cfg(test)]
+    pub fn from(links: Vec<(String, Box<dyn AuthenticationProxy>)>) -> Chain {
+        let links = links
+            .into_iter()
+            .map(|(name, proxy)| ChainLink { name, proxy })
+            .collect();
+        Chain { links }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for Chain {
+    async fn check(
+        &self,
+        context: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let mut denied: Option<AuthenticationResult> = None;
+        let mut error: Option<anyhow::Error> = None;
+        let mut must_login: Option<AuthenticationResult> = None;
+        for link in &self.links {
+            let mut result = match link.proxy.check(context, request).await {
+                Ok(result) => result,
+                Err(link_error) => {
+                    log::error!("Authenticator {} failed: {:?}", link.name, link_error);
+                    error.get_or_insert(link_error);
+                    continue;
+                }
+            };
+            result.authenticator = Some(link.name.clone());
+            match result.status {
+                AuthenticationStatus::Allowed => return Ok(result),
+                AuthenticationStatus::Denied => {
+                    denied.get_or_insert(result);
+                }
+                // Collect login headers (like `WWW-Authenticate`) from all proxies.
+                AuthenticationStatus::MustLogin => match must_login.as_mut() {
+                    None => must_login = Some(result),
+                    Some(must_login) => {
+                        for (name, value) in result.headers.iter() {
+                            must_login.headers.append(name.clone(), value.clone());
+                        }
+                    }
+                },
+            }
+        }
+        match (denied.or(must_login), error) {
+            (Some(result), _) => Ok(result),
+            (None, Some(error)) => Err(error),
+            (None, None) => anyhow::bail!("no authenticator configured to check requests"),
+        }
+    }
+}
+
+/// Instantiate per-thread `Chain` instances.
+pub struct ChainFactory {
+    links: Vec<(String, Arc<dyn AuthenticationProxyFactory>)>,
+}
+
+impl ChainFactory {
+    /// Create a chain factory from a list of named authentication proxy factories.
+    pub fn new(links: Vec<(String, Arc<dyn AuthenticationProxyFactory>)>) -> ChainFactory {
+        ChainFactory { links }
+    }
+}
+
+impl AuthenticationProxyFactory for ChainFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        let links = self
+            .links
+            .iter()
+            .map(|(name, factory)| ChainLink {
+                name: name.clone(),
+                proxy: factory.make(),
+            })
+            .collect();
+        Box::new(Chain { links })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use anyhow::Result;
+
+    use super::Chain;
+    use crate::authenticator::tests::Authenticator;
+    use crate::authenticator::AuthenticationProxy;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    async fn check(links: Vec<(&str, Authenticator)>) -> AuthenticationResult {
+        try_check(links).await.unwrap()
+    }
+
+    async fn try_check(links: Vec<(&str, Authenticator)>) -> Result<AuthenticationResult> {
+        let links = links
+            .into_iter()
+            .map(|(name, auth)| {
+                let auth: Box<dyn AuthenticationProxy> = Box::new(auth);
+                (name.to_string(), auth)
+            })
+            .collect();
+        let request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"))
+            .to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        Chain::from(links).check(&context, &request).await
+    }
+
+    #[actix_rt::test]
+    async fn first_allowed_wins() {
+        let result = check(vec![
+            ("first", Authenticator::must_login()),
+            ("second", Authenticator::alice()),
+            ("third", Authenticator::denied()),
+        ])
+        .await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authenticator, Some("second".into()));
+        assert_eq!(result.authentication_context.user, Some("alice".into()));
+    }
+
+    #[actix_rt::test]
+    async fn denied_over_must_login() {
+        let result = check(vec![
+            ("first", Authenticator::must_login()),
+            ("second", Authenticator::denied()),
+        ])
+        .await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+        assert_eq!(result.authenticator, Some("second".into()));
+    }
+
+    #[actix_rt::test]
+    async fn must_login_if_all_must_login() {
+        let result = check(vec![
+            ("first", Authenticator::must_login()),
+            ("second", Authenticator::must_login()),
+        ])
+        .await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert_eq!(result.authenticator, Some("first".into()));
+        let headers: Vec<_> = result.headers.get_all("x-authenticator").collect();
+        assert_eq!(headers.len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn errors_are_propagated() {
+        let result = try_check(vec![("failing", Authenticator::failing())]).await;
+        assert!(result.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn errors_are_skipped() {
+        let result = check(vec![
+            ("failing", Authenticator::failing()),
+            ("second", Authenticator::alice()),
+        ])
+        .await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authenticator, Some("second".into()));
+        let result = check(vec![
+            ("failing", Authenticator::failing()),
+            ("second", Authenticator::must_login()),
+        ])
+        .await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use actix_web::HttpRequest;
+use anyhow::Context;
+use anyhow::Result;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+use x509_parser::pem::Pem;
+
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::ClientCertConfig;
+use crate::config::ClientCertUserSource;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// Value of the verify header when the proxy successfully verified the client certificate.
+const VERIFY_SUCCESS: &str = "SUCCESS";
+
+/// Value of the verify header when the client did not present a certificate.
+const VERIFY_NONE: &str = "NONE";
+
+/// Revoked certificates identified by issuer (raw DER name) and serial number (raw DER bytes).
+type RevokedSet = HashSet<(Vec<u8>, Vec<u8>)>;
+
+/// Load DER encoded CA certificates from a PEM bundle.
+fn load_ca_bundle(path: &str) -> Result<Vec<Vec<u8>>> {
+    let bundle = std::fs::read(path)
+        .with_context(|| format!("Unable to read CA certificates from {}", path))?;
+    let mut cas = Vec::new();
+    for pem in Pem::iter_from_buffer(&bundle) {
+        let pem = pem.with_context(|| format!("Unable to decode PEM CA bundle {}", path))?;
+        pem.parse_x509()
+            .with_context(|| format!("Unable to parse CA certificate in {}", path))?;
+        cas.push(pem.contents);
+    }
+    if cas.is_empty() {
+        anyhow::bail!("no CA certificates found in {}", path);
+    }
+    Ok(cas)
+}
+
+/// Load the revoked certificates from a PEM or DER certificate revocation list.
+///
+/// If CA certificates are given the revocation list must be signed by one of them.
+fn load_crl(path: &str, cas: &[Vec<u8>]) -> Result<RevokedSet> {
+    let crl = std::fs::read(path)
+        .with_context(|| format!("Unable to read certificate revocation list from {}", path))?;
+    let der = if crl.starts_with(b"-----BEGIN") {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(&crl)
+            .map_err(|error| anyhow::anyhow!("{:?}", error))
+            .with_context(|| format!("Unable to decode PEM revocation list {}", path))?;
+        pem.contents
+    } else {
+        crl
+    };
+    let (_, crl) = x509_parser::parse_x509_crl(&der)
+        .map_err(|error| anyhow::anyhow!("{:?}", error))
+        .with_context(|| format!("Unable to parse certificate revocation list {}", path))?;
+
+    if !cas.is_empty() {
+        let verified = parse_cas(cas).iter().any(|ca| {
+            ca.subject().as_raw() == crl.issuer().as_raw()
+                && crl.verify_signature(ca.public_key()).is_ok()
+        });
+        if !verified {
+            anyhow::bail!(
+                "certificate revocation list {} is not signed by a configured CA",
+                path
+            );
+        }
+    }
+
+    let issuer = crl.issuer().as_raw().to_vec();
+    let revoked = crl
+        .iter_revoked_certificates()
+        .map(|revoked| (issuer.clone(), revoked.raw_serial().to_vec()))
+        .collect();
+    Ok(revoked)
+}
+
+/// Parse DER encoded CA certificates validated when they were loaded.
+fn parse_cas(cas: &[Vec<u8>]) -> Vec<X509Certificate<'_>> {
+    cas.iter()
+        .filter_map(|der| x509_parser::parse_x509_certificate(der).ok())
+        .map(|(_, ca)| ca)
+        .collect()
+}
+
+/// Extract the Common Name from a subject DN forwarded by the proxy.
+///
+/// Both RFC 2253 (`CN=name,O=org`) and legacy (`/O=org/CN=name`) formats are supported.
+fn dn_common_name(dn: &str) -> Option<String> {
+    let separator = if dn.starts_with('/') { '/' } else { ',' };
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut escaped = false;
+    for c in dn.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == separator && !escaped => parts.push(std::mem::take(&mut part)),
+            c => {
+                escaped = false;
+                part.push(c);
+            }
+        }
+    }
+    parts.push(part);
+    parts.into_iter().find_map(|part| {
+        let (key, value) = part.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("cn") && !value.is_empty() {
+            Some(value.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Derive the user ID from the configured certificate field.
+fn certificate_user(cert: &X509Certificate<'_>, source: ClientCertUserSource) -> Option<String> {
+    let names = match source {
+        ClientCertUserSource::SubjectCn => {
+            let cn = cert.subject().iter_common_name().next()?;
+            return cn.as_str().ok().map(String::from);
+        }
+        _ => cert.subject_alternative_name().ok()??,
+    };
+    names
+        .value
+        .general_names
+        .iter()
+        .find_map(|name| match (source, name) {
+            (ClientCertUserSource::SanDns, GeneralName::DNSName(name)) => Some(name.to_string()),
+            (ClientCertUserSource::SanEmail, GeneralName::RFC822Name(name)) => {
+                Some(name.to_string())
+            }
+            (ClientCertUserSource::SanUri, GeneralName::URI(name)) => Some(name.to_string()),
+            _ => None,
+        })
+}
+
+/// Attempt to extract the named header from the request.
+fn extract_header<'request>(
+    request: &'request HttpRequest,
+    header: &Option<String>,
+) -> Option<&'request str> {
+    let header = header.as_ref()?;
+    let value = request.headers().get(header)?.to_str().ok()?.trim();
+    if value.is_empty() || value == "(null)" || value == "-" {
+        return None;
+    }
+    Some(value)
+}
+
+/// Authenticate requests with client certificate details forwarded by the HTTPS proxy.
+pub struct ClientCert {
+    cas: Arc<Vec<Vec<u8>>>,
+    config: ClientCertConfig,
+    revoked: Arc<RevokedSet>,
+}
+
+impl ClientCert {
+    /// Authenticate the request with the client certificate in URL-encoded PEM format.
+    fn check_certificate(&self, pem: &str) -> Result<AuthenticationResult> {
+        let pem = percent_encoding::percent_decode_str(pem)
+            .decode_utf8()
+            .context("client certificate is not valid UTF8")?;
+        let (_, pem) = x509_parser::pem::parse_x509_pem(pem.as_bytes())
+            .map_err(|error| anyhow::anyhow!("unable to decode PEM: {:?}", error))?;
+        let cert = pem
+            .parse_x509()
+            .map_err(|error| anyhow::anyhow!("unable to parse certificate: {:?}", error))?;
+
+        // Validate the certificate, its issuer and revocation status.
+        if !cert.validity().is_valid() {
+            anyhow::bail!("certificate is expired or not yet valid");
+        }
+        if !self.cas.is_empty() {
+            let trusted = parse_cas(&self.cas).iter().any(|ca| {
+                ca.subject().as_raw() == cert.issuer().as_raw()
+                    && cert.verify_signature(Some(ca.public_key())).is_ok()
+            });
+            if !trusted {
+                anyhow::bail!("certificate is not issued by a trusted CA");
+            }
+        }
+        let id = (cert.issuer().as_raw().to_vec(), cert.raw_serial().to_vec());
+        if self.revoked.contains(&id) {
+            anyhow::bail!("certificate {} is revoked", cert.raw_serial_as_string());
+        }
+
+        // Extract user information from the certificate.
+        let user = certificate_user(&cert, self.config.user_source).ok_or_else(|| {
+            anyhow::anyhow!(
+                "certificate has no {:?} to derive the user from",
+                self.config.user_source
+            )
+        })?;
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        let context = &mut result.authentication_context;
+        context.authenticated = true;
+        context.user = Some(user);
+        context
+            .attributes
+            .insert("issuer".into(), cert.issuer().to_string());
+        context
+            .attributes
+            .insert("serial".into(), cert.raw_serial_as_string());
+        context
+            .attributes
+            .insert("subject".into(), cert.subject().to_string());
+        Ok(result)
+    }
+
+    /// Authenticate the request with the client certificate subject DN.
+    fn check_subject(&self, dn: &str) -> Result<AuthenticationResult> {
+        if !self.config.allow_subject_dn_only {
+            anyhow::bail!("subject DN only authentication is not allowed");
+        }
+        if !self.cas.is_empty() || !self.revoked.is_empty() {
+            anyhow::bail!("the client certificate is required to verify it");
+        }
+        if self.config.user_source != ClientCertUserSource::SubjectCn {
+            anyhow::bail!("the client certificate is required to read its SAN");
+        }
+        let user =
+            dn_common_name(dn).ok_or_else(|| anyhow::anyhow!("subject DN has no common name"))?;
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        let context = &mut result.authentication_context;
+        context.authenticated = true;
+        context.user = Some(user);
+        context.attributes.insert("subject".into(), dn.to_string());
+        Ok(result)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for ClientCert {
+    async fn check(
+        &self,
+        _: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        // Check the proxy verified the client certificate, if it tells us.
+        if self.config.verify_header.is_some() {
+            match extract_header(request, &self.config.verify_header) {
+                None | Some(VERIFY_NONE) => {
+                    return Ok(AuthenticationResult::from_status(
+                        AuthenticationStatus::MustLogin,
+                    ))
+                }
+                Some(VERIFY_SUCCESS) => (),
+                Some(verify) => {
+                    log::debug!("Client certificate verification failed: {}", verify);
+                    return Ok(AuthenticationResult::denied());
+                }
+            }
+        }
+
+        let result = if let Some(pem) = extract_header(request, &self.config.cert_header) {
+            self.check_certificate(pem)
+        } else if let Some(dn) = extract_header(request, &self.config.subject_dn_header) {
+            self.check_subject(dn)
+        } else {
+            return Ok(AuthenticationResult::from_status(
+                AuthenticationStatus::MustLogin,
+            ));
+        };
+        match result {
+            Ok(result) => Ok(result),
+            Err(error) => {
+                log::warn!("Rejected client certificate: {:?}", error);
+                Ok(AuthenticationResult::denied())
+            }
+        }
+    }
+}
+
+/// Instantiate per-thread `ClientCert` instances sharing the same CAs and revocation list.
+pub struct ClientCertFactory {
+    cas: Arc<Vec<Vec<u8>>>,
+    config: ClientCertConfig,
+    revoked: Arc<RevokedSet>,
+}
+
+impl ClientCertFactory {
+    /// Load the CA certificates and revocation list from the configured files.
+    pub fn from_config(config: &ClientCertConfig) -> Result<ClientCertFactory> {
+        let requires_cert = config.ca_file.is_some() || config.crl_file.is_some();
+        if requires_cert && config.cert_header.is_none() {
+            anyhow::bail!("client-cert authenticator needs a `cert-header` to verify certificates");
+        }
+        let cas = match &config.ca_file {
+            None => Vec::new(),
+            Some(path) => load_ca_bundle(path)?,
+        };
+        let revoked = match &config.crl_file {
+            None => RevokedSet::new(),
+            Some(path) => load_crl(path, &cas)?,
+        };
+        Ok(ClientCertFactory {
+            cas: Arc::new(cas),
+            config: config.clone(),
+            revoked: Arc::new(revoked),
+        })
+    }
+}
+
+impl AuthenticationProxyFactory for ClientCertFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        Box::new(ClientCert {
+            cas: Arc::clone(&self.cas),
+            config: self.config.clone(),
+            revoked: Arc::clone(&self.revoked),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::dn_common_name;
+    use super::ClientCertFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::ClientCertConfig;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    fn factory(config: &str) -> ClientCertFactory {
+        let config: ClientCertConfig = serde_yaml::from_str(config).unwrap();
+        ClientCertFactory::from_config(&config).unwrap()
+    }
+
+    fn verified() -> ClientCertFactory {
+        factory(
+            r#"
+            ca-file: tests/fixtures/mtls/ca.pem
+            crl-file: tests/fixtures/mtls/crl.pem
+            "#,
+        )
+    }
+
+    fn pem(name: &str) -> String {
+        let pem = std::fs::read_to_string(format!("tests/fixtures/mtls/{}.pem", name)).unwrap();
+        percent_encoding::utf8_percent_encode(&pem, percent_encoding::NON_ALPHANUMERIC).to_string()
+    }
+
+    async fn check(factory: &ClientCertFactory, headers: &[(&str, &str)]) -> AuthenticationResult {
+        let mut request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        for header in headers {
+            request = request.append_header(*header);
+        }
+        let request = request.to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        factory.make().check(&context, &request).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn certificate_allowed() {
+        let cert = pem("client");
+        let headers = [
+            ("X-SSL-Client-Verify", "SUCCESS"),
+            ("X-SSL-Client-Cert", &cert),
+        ];
+        let result = check(&verified(), &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        let context = &result.authentication_context;
+        assert!(context.authenticated);
+        assert_eq!(context.user, Some("device-1".into()));
+        assert_eq!(
+            context.attributes["subject"],
+            "O=AuthGateway Tests, CN=device-1"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn certificate_revoked() {
+        let cert = pem("revoked");
+        let headers = [
+            ("X-SSL-Client-Verify", "SUCCESS"),
+            ("X-SSL-Client-Cert", &cert),
+        ];
+        let result = check(&verified(), &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn certificate_untrusted() {
+        let cert = pem("untrusted");
+        let headers = [
+            ("X-SSL-Client-Verify", "SUCCESS"),
+            ("X-SSL-Client-Cert", &cert),
+        ];
+        let result = check(&verified(), &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn certificate_user_from_san() {
+        let factory = factory("user-source: san-email");
+        let cert = pem("untrusted");
+        let headers = [
+            ("X-SSL-Client-Verify", "SUCCESS"),
+            ("X-SSL-Client-Cert", &cert),
+        ];
+        let result = check(&factory, &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(
+            result.authentication_context.user,
+            Some("device@example.com".into())
+        );
+    }
+
+    #[test]
+    fn common_name_from_dn() {
+        assert_eq!(
+            dn_common_name("CN=device-1,O=AuthGateway Tests"),
+            Some("device-1".into())
+        );
+        assert_eq!(
+            dn_common_name("/O=AuthGateway Tests/CN=device-1"),
+            Some("device-1".into())
+        );
+        assert_eq!(
+            dn_common_name("CN=Doe\\, John,O=Example"),
+            Some("Doe, John".into())
+        );
+        assert_eq!(dn_common_name("O=Example"), None);
+    }
+
+    #[actix_rt::test]
+    async fn missing_certificate() {
+        let headers = [("X-SSL-Client-Verify", "NONE")];
+        let result = check(&verified(), &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[actix_rt::test]
+    async fn proxy_verification_failed() {
+        let cert = pem("client");
+        let headers = [
+            ("X-SSL-Client-Verify", "FAILED:certificate has expired"),
+            ("X-SSL-Client-Cert", &cert),
+        ];
+        let result = check(&verified(), &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn subject_dn_allowed() {
+        let factory = factory("allow-subject-dn-only: true");
+        let headers = [
+            ("X-SSL-Client-Verify", "SUCCESS"),
+            ("X-SSL-Client-S-DN", "CN=device-1,O=AuthGateway Tests"),
+        ];
+        let result = check(&factory, &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authentication_context.user, Some("device-1".into()));
+    }
+
+    #[actix_rt::test]
+    async fn subject_dn_needs_opt_in() {
+        let factory = factory("{}");
+        let headers = [
+            ("X-SSL-Client-Verify", "SUCCESS"),
+            ("X-SSL-Client-S-DN", "CN=admin"),
+        ];
+        let result = check(&factory, &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn subject_dn_needs_certificate_to_verify() {
+        let headers = [
+            ("X-SSL-Client-Verify", "SUCCESS"),
+            ("X-SSL-Client-S-DN", "CN=device-1,O=AuthGateway Tests"),
+        ];
+        let result = check(&verified(), &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use actix_web::http::header::HeaderName;
+use actix_web::HttpRequest;
+use anyhow::Result;
+use awc::Client;
+
+use crate::authenticator::http::copy_headers;
+use crate::authenticator::http::extract_header;
+use crate::authenticator::http::extract_list;
+use crate::authenticator::http::forward;
+use crate::authenticator::http::parse_header_names;
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::ForwardAuthConfig;
+use crate::models::AuthenticationResult;
+use crate::models::RequestContext;
+
+/// Authenticate requests by forwarding them to a generic HTTP authentication service.
+pub struct ForwardAuth {
+    client: Client,
+    config: ForwardAuthConfig,
+    response_headers: Vec<HeaderName>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for ForwardAuth {
+    async fn check(
+        &self,
+        _: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let response = forward(
+            &self.client,
+            &self.config.url,
+            request,
+            &self.config.statuses,
+            "the forward authentication service",
+        )
+        .await;
+        let (status, headers) = match response {
+            Some(response) => response,
+            None => return Ok(AuthenticationResult::denied()),
+        };
+
+        // Extract user information from the response.
+        let mut result = AuthenticationResult::from_status(status);
+        let context = &mut result.authentication_context;
+        context.authenticated = status.authenticated();
+        if let Some(header) = &self.config.user_header {
+            context.user = extract_header(&headers, header);
+        }
+        if let Some(header) = &self.config.email_header {
+            if let Some(email) = extract_header(&headers, header) {
+                context.attributes.insert("email".into(), email);
+            }
+        }
+        if let Some(header) = &self.config.groups_header {
+            context.groups = extract_list(&headers, header, &self.config.groups_separator);
+        }
+        if let Some(header) = &self.config.session_header {
+            context.session = extract_header(&headers, header)
+                .map(|session| crate::authenticator::hash_token(&session));
+        }
+        copy_headers(&headers, &self.response_headers, &mut result.headers);
+        Ok(result)
+    }
+}
+
+/// Instantiate per-thread `ForwardAuth` instances.
+pub struct ForwardAuthFactory {
+    config: ForwardAuthConfig,
+    response_headers: Vec<HeaderName>,
+}
+
+impl ForwardAuthFactory {
+    pub fn from_config(config: &ForwardAuthConfig) -> Result<ForwardAuthFactory> {
+        let response_headers = parse_header_names(&config.response_headers)?;
+        Ok(ForwardAuthFactory {
+            config: config.clone(),
+            response_headers,
+        })
+    }
+}
+
+impl AuthenticationProxyFactory for ForwardAuthFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        let client = Client::builder()
+            .disable_redirects()
+            .timeout(Duration::from_secs(self.config.timeout_sec))
+            .finish();
+        Box::new(ForwardAuth {
+            client,
+            config: self.config.clone(),
+            response_headers: self.response_headers.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+
+    use super::ForwardAuthFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::ForwardAuthConfig;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    async fn verify(request: HttpRequest) -> HttpResponse {
+        let cookie = request
+            .headers()
+            .get("cookie")
+            .and_then(|cookie| cookie.to_str().ok());
+        match cookie {
+            Some("session=alice") => HttpResponse::Ok()
+                .append_header(("Remote-User", "alice"))
+                .append_header(("Remote-Email", "alice@example.com"))
+                .append_header(("Remote-Groups", "admins, sre"))
+                .append_header(("Set-Cookie", "session=alice; Max-Age=60"))
+                .finish(),
+            Some("session=bob") => HttpResponse::Forbidden().finish(),
+            Some(_) => HttpResponse::InternalServerError().body("broken"),
+            None => HttpResponse::Unauthorized().finish(),
+        }
+    }
+
+    fn configure(app: &mut web::ServiceConfig) {
+        app.route("/verify", web::get().to(verify));
+    }
+
+    async fn check(cookie: Option<&str>) -> AuthenticationResult {
+        let (address, server) = crate::authenticator::tests::mock_server(configure);
+        let config: ForwardAuthConfig = serde_yaml::from_str(&format!(
+            r#"
+            url: 'http://{}/verify'
+            response-headers: ['set-cookie']
+            "#,
+            address
+        ))
+        .unwrap();
+        let factory = ForwardAuthFactory::from_config(&config).unwrap();
+        let mut request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        if let Some(cookie) = cookie {
+            request = request.append_header(("Cookie", cookie));
+        }
+        let request = request.to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        let result = factory.make().check(&context, &request).await.unwrap();
+        server.stop(false).await;
+        result
+    }
+
+    #[actix_rt::test]
+    async fn allowed() {
+        let result = check(Some("session=alice")).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        let context = &result.authentication_context;
+        assert!(context.authenticated);
+        assert_eq!(context.user, Some("alice".into()));
+        assert_eq!(
+            context.attributes.get("email"),
+            Some(&"alice@example.com".to_string())
+        );
+        assert_eq!(context.groups, vec!["admins", "sre"]);
+        assert_eq!(
+            result.headers.get("set-cookie").unwrap(),
+            "session=alice; Max-Age=60"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn denied() {
+        let result = check(Some("session=bob")).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+        assert!(!result.authentication_context.authenticated);
+    }
+
+    #[actix_rt::test]
+    async fn denied_on_unexpected_status() {
+        let result = check(Some("session=broken")).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn must_login() {
+        let result = check(None).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert_eq!(result.authentication_context.user, None);
+    }
+}
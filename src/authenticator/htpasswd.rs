@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::read_to_string;
+use std::sync::Arc;
+
+use actix_web::http::header::HeaderValue;
+use actix_web::http::header::WWW_AUTHENTICATE;
+use actix_web::HttpRequest;
+use anyhow::Context;
+use anyhow::Result;
+use argon2::password_hash::SaltString;
+use argon2::Argon2;
+use argon2::PasswordHasher;
+use argon2::PasswordVerifier;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha1::Digest;
+use sha1::Sha1;
+
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::HtpasswdConfig;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// Password hashed to verify against for unknown users.
+const DUMMY_PASSWORD: &str = "authgateway-unknown-user";
+
+/// Password hashes supported in htpasswd files.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum PasswordHash {
+    /// PHC formatted argon2 hash.
+    Argon2(String),
+
+    /// Modular crypt formatted bcrypt hash.
+    Bcrypt(String),
+
+    /// Unsalted SHA1 digest (`{SHA}` prefix).
+    Sha1(Vec<u8>),
+}
+
+impl PasswordHash {
+    /// Hash a dummy password with the same algorithm and cost as the given hash.
+    ///
+    /// Unknown users are verified against this hash so they take as long as known users.
+    fn dummy_like(hash: &PasswordHash) -> Result<PasswordHash> {
+        match hash {
+            PasswordHash::Argon2(hash) => {
+                let hash = argon2::PasswordHash::new(hash)
+                    .map_err(|error| anyhow::anyhow!("invalid argon2 hash: {}", error))?;
+                let params = argon2::Params::try_from(&hash)
+                    .map_err(|error| anyhow::anyhow!("invalid argon2 params: {}", error))?;
+                let salt = SaltString::encode_b64(DUMMY_PASSWORD.as_bytes())
+                    .map_err(|error| anyhow::anyhow!("invalid argon2 salt: {}", error))?;
+                let dummy = Argon2::default()
+                    .hash_password_customized(
+                        DUMMY_PASSWORD.as_bytes(),
+                        Some(hash.algorithm),
+                        hash.version,
+                        params,
+                        &salt,
+                    )
+                    .map_err(|error| anyhow::anyhow!("unable to hash password: {}", error))?;
+                Ok(PasswordHash::Argon2(dummy.to_string()))
+            }
+            PasswordHash::Bcrypt(hash) => {
+                let cost = hash
+                    .get(4..6)
+                    .and_then(|cost| cost.parse().ok())
+                    .unwrap_or(bcrypt::DEFAULT_COST);
+                let dummy = bcrypt::hash(DUMMY_PASSWORD, cost)?;
+                Ok(PasswordHash::Bcrypt(dummy))
+            }
+            PasswordHash::Sha1(_) => Ok(PasswordHash::Sha1(
+                Sha1::digest(DUMMY_PASSWORD.as_bytes()).to_vec(),
+            )),
+        }
+    }
+
+    /// Hash a dummy password like the slowest hash kind found in the users.
+    fn dummy_for(users: &HashMap<String, PasswordHash>) -> Result<PasswordHash> {
+        let slowest = users
+            .values()
+            .find(|hash| matches!(hash, PasswordHash::Argon2(_)))
+            .or_else(|| {
+                users
+                    .values()
+                    .find(|hash| matches!(hash, PasswordHash::Bcrypt(_)))
+            })
+            .or_else(|| users.values().next());
+        match slowest {
+            Some(hash) => PasswordHash::dummy_like(hash),
+            None => Ok(PasswordHash::Sha1(Vec::new())),
+        }
+    }
+
+    /// Parse a password hash from an htpasswd entry.
+    fn parse(hash: &str) -> Result<PasswordHash> {
+        if hash.starts_with("$argon2") {
+            argon2::PasswordHash::new(hash)
+                .map_err(|error| anyhow::anyhow!("invalid argon2 hash: {}", error))?;
+            return Ok(PasswordHash::Argon2(hash.to_string()));
+        }
+        if hash.starts_with("$2a$") || hash.starts_with("$2b$") || hash.starts_with("$2y$") {
+            return Ok(PasswordHash::Bcrypt(hash.to_string()));
+        }
+        if let Some(digest) = hash.strip_prefix("{SHA}") {
+            let digest = BASE64
+                .decode(digest)
+                .with_context(|| "invalid base64 encoded SHA1 digest")?;
+            return Ok(PasswordHash::Sha1(digest));
+        }
+        anyhow::bail!("unsupported password hash format")
+    }
+
+    /// Check a password against the hash.
+    ///
+    /// Hashing functions are expensive by design so this should not block the event loop.
+    fn verify(&self, password: &str) -> bool {
+        match self {
+            PasswordHash::Argon2(hash) => match argon2::PasswordHash::new(hash) {
+                Ok(hash) => Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok(),
+                Err(_) => false,
+            },
+            PasswordHash::Bcrypt(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+            PasswordHash::Sha1(digest) => {
+                let actual = Sha1::digest(password.as_bytes());
+                // Compare all bytes to avoid leaking information through timing.
+                actual.len() == digest.len()
+                    && actual
+                        .iter()
+                        .zip(digest.iter())
+                        .fold(0, |diff, (a, b)| diff | (a ^ b))
+                        == 0
+            }
+        }
+    }
+}
+
+/// Parse the content of an htpasswd file into a map of users to password hashes.
+fn parse_htpasswd(content: &str) -> Result<HashMap<String, PasswordHash>> {
+    let mut users = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (user, hash) = line.split_once(':').ok_or_else(|| {
+            anyhow::anyhow!("line {} is not in the `user:hash` format", index + 1)
+        })?;
+        let hash = PasswordHash::parse(hash)
+            .with_context(|| format!("unable to load password for user '{}'", user))?;
+        users.insert(user.to_string(), hash);
+    }
+    Ok(users)
+}
+
+/// Authenticate users with HTTP Basic credentials checked against an htpasswd file.
+pub struct Htpasswd {
+    challenge: HeaderValue,
+    dummy: PasswordHash,
+    users: Arc<HashMap<String, PasswordHash>>,
+}
+
+impl Htpasswd {
+    /// Ask clients to provide (new) credentials.
+    fn must_login(&self) -> AuthenticationResult {
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::MustLogin);
+        result
+            .headers
+            .insert(WWW_AUTHENTICATE, self.challenge.clone());
+        result
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for Htpasswd {
+    async fn check(
+        &self,
+        _: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let credentials = match super::credentials::extract_basic(request) {
+            None => return Ok(self.must_login()),
+            Some(credentials) => credentials,
+        };
+        // Unknown users are verified against a dummy hash so they can't be told apart by timing.
+        let (known, hash) = match self.users.get(&credentials.user) {
+            None => (false, self.dummy.clone()),
+            Some(hash) => (true, hash.clone()),
+        };
+
+        // Verify the password in the thread pool as hashing is slow.
+        let password = credentials.password;
+        let valid = actix_web::web::block(move || hash.verify(&password)).await?;
+        if !known {
+            log::debug!("Basic credentials for unknown user '{}'", credentials.user);
+            return Ok(self.must_login());
+        }
+        if !valid {
+            log::debug!("Invalid password for user '{}'", credentials.user);
+            return Ok(self.must_login());
+        }
+
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        result.authentication_context.authenticated = true;
+        result.authentication_context.user = Some(credentials.user);
+        Ok(result)
+    }
+}
+
+/// Instantiate per-thread `Htpasswd` instances sharing the loaded users.
+pub struct HtpasswdFactory {
+    challenge: HeaderValue,
+    dummy: PasswordHash,
+    users: Arc<HashMap<String, PasswordHash>>,
+}
+
+impl HtpasswdFactory {
+    pub fn from_config(config: &HtpasswdConfig) -> Result<HtpasswdFactory> {
+        let content = read_to_string(&config.file)
+            .with_context(|| format!("Unable to load htpasswd file {}", config.file))?;
+        let users = parse_htpasswd(&content)
+            .with_context(|| format!("Unable to parse htpasswd file {}", config.file))?;
+        let challenge = super::credentials::basic_challenge(&config.realm)?;
+        let dummy = PasswordHash::dummy_for(&users)?;
+        Ok(HtpasswdFactory {
+            challenge,
+            dummy,
+            users: Arc::new(users),
+        })
+    }
+}
+
+impl AuthenticationProxyFactory for HtpasswdFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        Box::new(Htpasswd {
+            challenge: self.challenge.clone(),
+            dummy: self.dummy.clone(),
+            users: Arc::clone(&self.users),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::header::HeaderValue;
+    use actix_web::test::TestRequest;
+    use argon2::password_hash::SaltString;
+    use argon2::Argon2;
+    use argon2::PasswordHasher;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use sha1::Digest;
+    use sha1::Sha1;
+
+    use super::Htpasswd;
+    use super::PasswordHash;
+    use crate::authenticator::AuthenticationProxy;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    fn htpasswd() -> Htpasswd {
+        let bcrypt = bcrypt::hash("bcrypt-pass", 4).unwrap();
+        let sha = BASE64.encode(Sha1::digest(b"sha-pass"));
+        let salt = SaltString::from_b64("c29tZXNhbHR2YWx1ZQ").unwrap();
+        let argon2 = Argon2::default()
+            .hash_password(b"argon2-pass", &salt)
+            .unwrap()
+            .to_string();
+        let content = format!(
+            "# Test users\nalice:{}\nbob:{{SHA}}{}\n\ncarol:{}\n",
+            bcrypt, sha, argon2
+        );
+        let users = super::parse_htpasswd(&content).unwrap();
+        Htpasswd {
+            challenge: HeaderValue::from_static("Basic realm=\"Tests\""),
+            dummy: PasswordHash::dummy_for(&users).unwrap(),
+            users: Arc::new(users),
+        }
+    }
+
+    async fn check(authorization: Option<&str>) -> crate::models::AuthenticationResult {
+        let mut request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        if let Some(authorization) = authorization {
+            let authorization = format!("Basic {}", BASE64.encode(authorization));
+            request = request.append_header(("Authorization", authorization));
+        }
+        let request = request.to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        htpasswd().check(&context, &request).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn allow_argon2() {
+        let result = check(Some("carol:argon2-pass")).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authentication_context.user, Some("carol".into()));
+    }
+
+    #[actix_rt::test]
+    async fn allow_bcrypt() {
+        let result = check(Some("alice:bcrypt-pass")).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authentication_context.user, Some("alice".into()));
+    }
+
+    #[actix_rt::test]
+    async fn allow_sha() {
+        let result = check(Some("bob:sha-pass")).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authentication_context.user, Some("bob".into()));
+    }
+
+    #[actix_rt::test]
+    async fn must_login_without_credentials() {
+        let result = check(None).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        let challenge = result.headers.get("www-authenticate").unwrap();
+        assert_eq!(challenge, "Basic realm=\"Tests\"");
+    }
+
+    #[actix_rt::test]
+    async fn must_login_with_wrong_password() {
+        let result = check(Some("alice:sha-pass")).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert_eq!(result.authentication_context.user, None);
+        assert!(result.headers.contains_key("www-authenticate"));
+    }
+
+    #[test]
+    fn dummy_hash_matches_slowest_hash() {
+        let bcrypt = PasswordHash::Bcrypt(bcrypt::hash("bcrypt-pass", 5).unwrap());
+        match PasswordHash::dummy_like(&bcrypt).unwrap() {
+            PasswordHash::Bcrypt(hash) => assert!(hash.starts_with("$2b$05$")),
+            hash => panic!("unexpected dummy hash {:?}", hash),
+        }
+        match htpasswd().dummy {
+            PasswordHash::Argon2(hash) => assert!(hash.starts_with("$argon2id$")),
+            hash => panic!("unexpected dummy hash {:?}", hash),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn must_login_unknown_user() {
+        let result = check(Some("mallory:bcrypt-pass")).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert!(result.headers.contains_key("www-authenticate"));
+    }
+
+    #[test]
+    fn reject_unsupported_hash() {
+        let error = PasswordHash::parse("$apr1$salt$hash");
+        assert!(error.is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::HeaderMap;
+use actix_web::http::header::HeaderName;
+use actix_web::HttpRequest;
+use anyhow::Context;
+use anyhow::Result;
+use awc::Client;
+
+use crate::models::AuthenticationStatus;
+
+/// Attempt to extract the named header from response headers.
+pub fn extract_header(headers: &HeaderMap, header: &str) -> Option<String> {
+    match headers.get(header) {
+        None => None,
+        Some(value) => match String::from_utf8(value.as_bytes().to_vec()) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                log::error!("Unable to UTF8 decode header `{}`: {:?}", header, error);
+                None
+            }
+        },
+    }
+}
+
+/// Attempt to extract a list of values from a delimited response header.
+pub fn extract_list(headers: &HeaderMap, header: &str, separator: &str) -> Vec<String> {
+    extract_header(headers, header)
+        .map(|value| {
+            value
+                .split(separator)
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Copy all values of the given headers from one header map to another.
+pub fn copy_headers(from: &HeaderMap, names: &[HeaderName], to: &mut HeaderMap) {
+    for name in names {
+        for value in from.get_all(name) {
+            to.append(name.clone(), value.clone());
+        }
+    }
+}
+
+/// Parse a list of header names from the configuration.
+pub fn parse_header_names(names: &[String]) -> Result<Vec<HeaderName>> {
+    names
+        .iter()
+        .map(|name| {
+            HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("'{}' is not a valid header name", name))
+        })
+        .collect()
+}
+
+/// Forward the check request to an authentication service and map the response status.
+///
+/// Any error interacting with the service, including unexpected status codes, is logged
+/// and `None` is returned so the request can be denied.
+pub async fn forward(
+    client: &Client,
+    url: &str,
+    request: &HttpRequest,
+    statuses: &HashMap<u16, AuthenticationStatus>,
+    service: &str,
+) -> Option<(AuthenticationStatus, HeaderMap)> {
+    let response = client.request_from(url, request.head()).send().await;
+    let mut response = match response {
+        Ok(response) => response,
+        Err(error) => {
+            log::error!("Unable to check request with {}: {:?}", service, error);
+            return None;
+        }
+    };
+
+    // Validate response for HTTP-level errors (500, 404, etc ...).
+    match statuses.get(&response.status().as_u16()) {
+        Some(status) => Some((*status, response.headers().clone())),
+        None => {
+            let body = response.body().await;
+            log::error!(
+                "Unexpected status code from {}: {}",
+                service,
+                response.status()
+            );
+            log::debug!("Response body for unexpected status code: {:?}", body);
+            None
+        }
+    }
+}
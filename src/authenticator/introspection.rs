@@ -0,0 +1,331 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::HttpRequest;
+use anyhow::Result;
+use awc::Client;
+use serde::Deserialize;
+
+use crate::authenticator::cache::TtlCache;
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::IntrospectionConfig;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// Subset of the RFC 7662 introspection response used to authenticate requests.
+#[derive(Clone, Debug, Deserialize)]
+struct TokenInfo {
+    active: bool,
+    #[serde(default)]
+    exp: Option<i64>,
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    sub: Option<String>,
+    #[serde(default)]
+    username: Option<String>,
+}
+
+impl TokenInfo {
+    /// Check the token is active and, if an expiry time is known, not expired.
+    fn is_active(&self) -> bool {
+        let expired = self
+            .exp
+            .map(|exp| exp <= chrono::Utc::now().timestamp())
+            .unwrap_or(false);
+        self.active && !expired
+    }
+}
+
+/// Authenticate requests with opaque bearer tokens validated by an introspection endpoint.
+pub struct Introspection {
+    cache: Arc<TtlCache<TokenInfo>>,
+    client: Client,
+    config: IntrospectionConfig,
+}
+
+impl Introspection {
+    /// Request information about the token from the introspection endpoint.
+    async fn introspect(&self, token: &str) -> Result<TokenInfo> {
+        let form = [("token", token), ("token_type_hint", "access_token")];
+        let mut response = self
+            .client
+            .post(&self.config.url)
+            .basic_auth(&self.config.client_id, &self.config.client_secret)
+            .send_form(&form)
+            .await
+            .map_err(|error| anyhow::anyhow!("unable to reach endpoint: {:?}", error))?;
+        if !response.status().is_success() {
+            anyhow::bail!("unexpected status code {}", response.status());
+        }
+        let info = response
+            .json()
+            .await
+            .map_err(|error| anyhow::anyhow!("unable to decode response: {:?}", error))?;
+        Ok(info)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for Introspection {
+    async fn check(
+        &self,
+        _: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let token = match super::credentials::extract_bearer(request) {
+            None => {
+                return Ok(AuthenticationResult::from_status(
+                    AuthenticationStatus::MustLogin,
+                ))
+            }
+            Some(token) => token,
+        };
+
+        // Introspect tokens not found in the cache.
+        // Any errors interacting with the introspection endpoint is logged and requests are denied.
+        // Only active tokens are cached so random tokens can't evict valid sessions.
+        let session = crate::authenticator::hash_token(token);
+        let info = match self.cache.get(&session) {
+            Some(info) => info,
+            None => match self.introspect(token).await {
+                Ok(info) => {
+                    if info.is_active() {
+                        self.cache.insert(session.clone(), info.clone());
+                    }
+                    info
+                }
+                Err(error) => {
+                    log::error!("Unable to introspect token: {:?}", error);
+                    return Ok(AuthenticationResult::denied());
+                }
+            },
+        };
+        if !info.is_active() {
+            return Ok(AuthenticationResult::from_status(
+                AuthenticationStatus::MustLogin,
+            ));
+        }
+
+        // Extract user information from the token information.
+        let user = match info.sub.as_ref().or(info.username.as_ref()) {
+            Some(user) => user.clone(),
+            None => {
+                log::warn!("Active token is missing both the `sub` and `username` fields");
+                return Ok(AuthenticationResult::denied());
+            }
+        };
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        let context = &mut result.authentication_context;
+        context.authenticated = true;
+        context.user = Some(user);
+        context.session = Some(session);
+        if let Some(username) = info.username {
+            context.attributes.insert("username".into(), username);
+        }
+        if let Some(scope) = info.scope {
+            context.attributes.insert("scope".into(), scope);
+        }
+        Ok(result)
+    }
+}
+
+/// Instantiate per-thread `Introspection` instances sharing the same results cache.
+pub struct IntrospectionFactory {
+    cache: Arc<TtlCache<TokenInfo>>,
+    config: IntrospectionConfig,
+}
+
+impl IntrospectionFactory {
+    pub fn from_config(config: &IntrospectionConfig) -> IntrospectionFactory {
+        let cache = TtlCache::new(Duration::from_secs(config.cache_ttl_sec));
+        IntrospectionFactory {
+            cache: Arc::new(cache),
+            config: config.clone(),
+        }
+    }
+}
+
+impl AuthenticationProxyFactory for IntrospectionFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        let client = Client::builder()
+            .disable_redirects()
+            .timeout(Duration::from_secs(self.config.timeout_sec))
+            .finish();
+        Box::new(Introspection {
+            cache: Arc::clone(&self.cache),
+            client,
+            config: self.config.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+    use serde_json::json;
+
+    use super::IntrospectionFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::IntrospectionConfig;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    #[derive(serde::Deserialize)]
+    struct Form {
+        token: String,
+    }
+
+    async fn introspect(
+        request: HttpRequest,
+        form: web::Form<Form>,
+        calls: web::Data<AtomicUsize>,
+    ) -> HttpResponse {
+        calls.fetch_add(1, Ordering::SeqCst);
+        let auth = request
+            .headers()
+            .get("authorization")
+            .and_then(|auth| auth.to_str().ok());
+        // Basic base64("gateway:secret").
+        if auth != Some("Basic Z2F0ZXdheTpzZWNyZXQ=") {
+            return HttpResponse::Unauthorized().finish();
+        }
+        let info = match form.token.as_str() {
+            "active" => json!({
+                "active": true,
+                "scope": "read write",
+                "sub": "user-1234",
+                "username": "alice",
+            }),
+            "expired" => json!({"active": true, "exp": 1, "sub": "user-1234"}),
+            "anonymous" => json!({"active": true}),
+            _ => json!({"active": false}),
+        };
+        HttpResponse::Ok().json(info)
+    }
+
+    fn factory(url: String, cache_ttl_sec: u64) -> IntrospectionFactory {
+        let config: IntrospectionConfig = serde_yaml::from_str(&format!(
+            r#"
+            cache-ttl-sec: {}
+            client-id: gateway
+            client-secret: secret
+            url: '{}'
+            "#,
+            cache_ttl_sec, url
+        ))
+        .unwrap();
+        IntrospectionFactory::from_config(&config)
+    }
+
+    async fn check(factory: &IntrospectionFactory, token: Option<&str>) -> AuthenticationResult {
+        let mut request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        if let Some(token) = token {
+            request = request.append_header(("Authorization", format!("Bearer {}", token)));
+        }
+        let request = request.to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        factory.make().check(&context, &request).await.unwrap()
+    }
+
+    async fn check_tokens(
+        cache_ttl_sec: u64,
+        tokens: &[Option<&str>],
+    ) -> (Vec<AuthenticationResult>, usize) {
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let data = calls.clone();
+        let (address, server) = crate::authenticator::tests::mock_server(move |app| {
+            app.app_data(data.clone())
+                .route("/introspect", web::post().to(introspect));
+        });
+        let factory = factory(format!("http://{}/introspect", address), cache_ttl_sec);
+        let mut results = Vec::new();
+        for token in tokens {
+            results.push(check(&factory, *token).await);
+        }
+        server.stop(false).await;
+        (results, calls.load(Ordering::SeqCst))
+    }
+
+    #[actix_rt::test]
+    async fn active_token() {
+        let (results, _) = check_tokens(60, &[Some("active")]).await;
+        let result = &results[0];
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        let context = &result.authentication_context;
+        assert!(context.authenticated);
+        assert_eq!(context.user, Some("user-1234".into()));
+        assert_eq!(context.attributes["username"], "alice");
+        assert_eq!(context.attributes["scope"], "read write");
+        assert!(context.session.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn cache_results() {
+        let tokens = [Some("active"), Some("active"), Some("inactive")];
+        let (results, calls) = check_tokens(60, &tokens).await;
+        assert_eq!(results[1].status, AuthenticationStatus::Allowed);
+        assert_eq!(calls, 2);
+    }
+
+    #[actix_rt::test]
+    async fn cache_skips_inactive_tokens() {
+        let tokens = [Some("inactive"), Some("inactive")];
+        let (results, calls) = check_tokens(60, &tokens).await;
+        assert_eq!(results[1].status, AuthenticationStatus::MustLogin);
+        assert_eq!(calls, 2);
+    }
+
+    #[actix_rt::test]
+    async fn cache_disabled() {
+        let (_, calls) = check_tokens(0, &[Some("active"), Some("active")]).await;
+        assert_eq!(calls, 2);
+    }
+
+    #[actix_rt::test]
+    async fn endpoint_errors_deny() {
+        let factory = factory("http://127.0.0.1:1/introspect".into(), 60);
+        let result = check(&factory, Some("active")).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn expired_token() {
+        let (results, _) = check_tokens(60, &[Some("expired")]).await;
+        assert_eq!(results[0].status, AuthenticationStatus::MustLogin);
+    }
+
+    #[actix_rt::test]
+    async fn inactive_token() {
+        let (results, _) = check_tokens(60, &[Some("inactive")]).await;
+        assert_eq!(results[0].status, AuthenticationStatus::MustLogin);
+        assert!(!results[0].authentication_context.authenticated);
+    }
+
+    #[actix_rt::test]
+    async fn missing_token() {
+        let (results, calls) = check_tokens(60, &[None]).await;
+        assert_eq!(results[0].status, AuthenticationStatus::MustLogin);
+        assert_eq!(calls, 0);
+    }
+
+    #[actix_rt::test]
+    async fn missing_user() {
+        let (results, _) = check_tokens(60, &[Some("anonymous")]).await;
+        assert_eq!(results[0].status, AuthenticationStatus::Denied);
+    }
+}
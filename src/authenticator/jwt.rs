@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use actix_web::HttpRequest;
+use anyhow::Context;
+use anyhow::Result;
+use awc::Client;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::Validation;
+use serde_json::Value;
+
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::JwtConfig;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// Maximum size of JSON Web Key Set documents fetched from URLs.
+const JWKS_SIZE_LIMIT: usize = 1024 * 1024;
+
+/// JSON Web Key Set shared across threads and refreshed in the background.
+type SharedJwks = Arc<RwLock<JwkSet>>;
+
+/// Location to (re)load the JSON Web Key Set from.
+#[derive(Clone, Debug)]
+enum JwksSource {
+    File(String),
+    Url { timeout: Duration, url: String },
+}
+
+impl JwksSource {
+    /// Determine the JSON Web Key Set source from the configuration.
+    fn from_config(config: &JwtConfig) -> Result<JwksSource> {
+        match (&config.jwks_file, &config.jwks_url) {
+            (Some(file), None) => Ok(JwksSource::File(file.clone())),
+            (None, Some(url)) => Ok(JwksSource::Url {
+                timeout: Duration::from_secs(config.timeout_sec),
+                url: url.clone(),
+            }),
+            _ => anyhow::bail!("exactly one of jwks-file or jwks-url must be set"),
+        }
+    }
+
+    /// Load the current JSON Web Key Set from the source.
+    async fn load(&self) -> Result<JwkSet> {
+        match self {
+            JwksSource::File(file) => {
+                let jwks = std::fs::File::open(file)
+                    .with_context(|| format!("Unable to load JWKS from {}", file))?;
+                let jwks = serde_json::from_reader(jwks)
+                    .with_context(|| format!("Unable to JSON decode JWKS from {}", file))?;
+                Ok(jwks)
+            }
+            JwksSource::Url { timeout, url } => {
+                let client = Client::builder().timeout(*timeout).finish();
+                let mut response = client
+                    .get(url)
+                    .send()
+                    .await
+                    .map_err(|error| anyhow::anyhow!("Unable to fetch JWKS: {}", error))?;
+                if !response.status().is_success() {
+                    anyhow::bail!(
+                        "Unexpected status code fetching JWKS: {}",
+                        response.status()
+                    );
+                }
+                let jwks = response
+                    .json()
+                    .limit(JWKS_SIZE_LIMIT)
+                    .await
+                    .map_err(|error| anyhow::anyhow!("Unable to JSON decode JWKS: {}", error))?;
+                Ok(jwks)
+            }
+        }
+    }
+}
+
+/// Periodically reload the JSON Web Key Set, keeping the current keys on errors.
+async fn refresh_jwks(source: JwksSource, jwks: SharedJwks, interval: Duration) {
+    let mut interval = actix_web::rt::time::interval(interval);
+    // The first tick completes immediately and keys were just loaded.
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let update = match source.load().await {
+            Ok(update) => update,
+            Err(error) => {
+                log::error!("Unable to refresh JWKS, keeping current keys: {:?}", error);
+                continue;
+            }
+        };
+        match jwks.write() {
+            Ok(mut jwks) => *jwks = update,
+            Err(_) => log::error!("Unable to refresh JWKS: lock is poisoned"),
+        }
+    }
+}
+
+/// Authenticate requests with JWT bearer tokens validated against a JSON Web Key Set.
+pub struct Jwt {
+    algorithms: Vec<Algorithm>,
+    jwks: SharedJwks,
+    user_claim: String,
+    validation: Validation,
+}
+
+impl Jwt {
+    /// Validate the token and return its claims.
+    fn validate(&self, token: &str) -> Result<HashMap<String, Value>> {
+        let header = jsonwebtoken::decode_header(token)?;
+        if !self.algorithms.contains(&header.alg) {
+            anyhow::bail!("token algorithm {:?} is not allowed", header.alg);
+        }
+
+        // Find the key the token was signed with.
+        let key = {
+            let jwks = self
+                .jwks
+                .read()
+                .map_err(|_| anyhow::anyhow!("JWKS lock is poisoned"))?;
+            let jwk = match &header.kid {
+                Some(kid) => jwks.find(kid),
+                None if jwks.keys.len() == 1 => jwks.keys.first(),
+                None => None,
+            };
+            let jwk = jwk.ok_or_else(|| anyhow::anyhow!("no key found to validate token"))?;
+            DecodingKey::from_jwk(jwk)?
+        };
+
+        // Validate the token signature and claims.
+        let mut validation = self.validation.clone();
+        validation.algorithms = vec![header.alg];
+        let token = jsonwebtoken::decode(token, &key, &validation)?;
+        Ok(token.claims)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for Jwt {
+    async fn check(
+        &self,
+        _: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let token = match super::credentials::extract_bearer(request) {
+            None => {
+                return Ok(AuthenticationResult::from_status(
+                    AuthenticationStatus::MustLogin,
+                ))
+            }
+            Some(token) => token,
+        };
+        let claims = match self.validate(token) {
+            Ok(claims) => claims,
+            Err(error) => {
+                log::debug!("Rejected invalid JWT: {:?}", error);
+                return Ok(AuthenticationResult::from_status(
+                    AuthenticationStatus::MustLogin,
+                ));
+            }
+        };
+
+        // Extract the user ID from the token claims.
+        let user = match claims.get(&self.user_claim).and_then(Value::as_str) {
+            Some(user) => user.to_string(),
+            None => {
+                log::warn!(
+                    "Valid JWT is missing the '{}' string claim for the user ID",
+                    self.user_claim
+                );
+                return Ok(AuthenticationResult::denied());
+            }
+        };
+
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        result.authentication_context.authenticated = true;
+        result.authentication_context.user = Some(user);
+        result.authentication_context.session = Some(crate::authenticator::hash_token(token));
+        Ok(result)
+    }
+}
+
+/// Instantiate per-thread `Jwt` instances sharing the same JSON Web Key Set.
+pub struct JwtFactory {
+    algorithms: Vec<Algorithm>,
+    jwks: SharedJwks,
+    user_claim: String,
+    validation: Validation,
+}
+
+impl JwtFactory {
+    /// Load the JSON Web Key Set and start refreshing it in the background.
+    pub async fn from_config(config: &JwtConfig) -> Result<JwtFactory> {
+        if config.audience.is_empty() {
+            anyhow::bail!("jwt authenticator needs at least one `audience` to accept");
+        }
+        if config.issuer.is_empty() {
+            anyhow::bail!("jwt authenticator needs at least one `issuer` to accept");
+        }
+        let source = JwksSource::from_config(config)?;
+        let jwks = source.load().await?;
+        let jwks = Arc::new(RwLock::new(jwks));
+        if config.jwks_refresh_sec > 0 {
+            let interval = Duration::from_secs(config.jwks_refresh_sec);
+            actix_web::rt::spawn(refresh_jwks(source, Arc::clone(&jwks), interval));
+        }
+        Ok(JwtFactory::with_jwks(config, jwks))
+    }
+
+    /// Create a factory that validates tokens against the given JSON Web Key Set.
+    fn with_jwks(config: &JwtConfig, jwks: SharedJwks) -> JwtFactory {
+        let algorithms = config
+            .algorithms
+            .iter()
+            .map(|algorithm| (*algorithm).into())
+            .collect();
+        let mut validation = Validation::default();
+        validation.leeway = config.leeway_sec;
+        validation.validate_nbf = true;
+        validation.set_audience(&config.audience);
+        validation.set_issuer(&config.issuer);
+        validation.set_required_spec_claims(&["aud", "exp", "iss"]);
+        JwtFactory {
+            algorithms,
+            jwks,
+            user_claim: config.user_claim.clone(),
+            validation,
+        }
+    }
+}
+
+impl AuthenticationProxyFactory for JwtFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        Box::new(Jwt {
+            algorithms: self.algorithms.clone(),
+            jwks: Arc::clone(&self.jwks),
+            user_claim: self.user_claim.clone(),
+            validation: self.validation.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::RwLock;
+
+    use actix_web::test::TestRequest;
+    use jsonwebtoken::EncodingKey;
+    use jsonwebtoken::Header;
+    use serde_json::json;
+
+    use super::JwksSource;
+    use super::JwtFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::JwtConfig;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    const SECRET: &[u8] = b"test-secret-for-hs256-signatures";
+
+    fn config() -> JwtConfig {
+        serde_yaml::from_str(
+            r#"
+            algorithms: ['HS256']
+            jwks-file: tests/fixtures/jwks.json
+            audience: ['authgateway']
+            issuer: ['https://issuer.example.com']
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn token(claims: serde_json::Value) -> String {
+        let mut header = Header::new(jsonwebtoken::Algorithm::HS256);
+        header.kid = Some("test-key".into());
+        let key = EncodingKey::from_secret(SECRET);
+        jsonwebtoken::encode(&header, &claims, &key).unwrap()
+    }
+
+    fn valid_claims() -> serde_json::Value {
+        let now = jsonwebtoken::get_current_timestamp();
+        json!({
+            "aud": "authgateway",
+            "exp": now + 600,
+            "iss": "https://issuer.example.com",
+            "nbf": now - 10,
+            "sub": "service-a",
+        })
+    }
+
+    async fn check(token: Option<String>) -> AuthenticationResult {
+        let config = config();
+        let jwks = JwksSource::from_config(&config)
+            .unwrap()
+            .load()
+            .await
+            .unwrap();
+        let factory = JwtFactory::with_jwks(&config, Arc::new(RwLock::new(jwks)));
+        let mut request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        if let Some(token) = token {
+            request = request.append_header(("Authorization", format!("Bearer {}", token)));
+        }
+        let request = request.to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        factory.make().check(&context, &request).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn allow_valid_token() {
+        let token = token(valid_claims());
+        let result = check(Some(token.clone())).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        assert_eq!(result.authentication_context.user, Some("service-a".into()));
+        assert_eq!(
+            result.authentication_context.session,
+            Some(crate::authenticator::hash_token(&token))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn allow_within_clock_skew() {
+        let mut claims = valid_claims();
+        claims["exp"] = json!(jsonwebtoken::get_current_timestamp() - 30);
+        let result = check(Some(token(claims))).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+    }
+
+    #[actix_rt::test]
+    async fn must_login_expired_token() {
+        let mut claims = valid_claims();
+        claims["exp"] = json!(jsonwebtoken::get_current_timestamp() - 600);
+        let result = check(Some(token(claims))).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[actix_rt::test]
+    async fn must_login_wrong_audience() {
+        let mut claims = valid_claims();
+        claims["aud"] = json!("someone-else");
+        let result = check(Some(token(claims))).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[actix_rt::test]
+    async fn must_login_wrong_issuer() {
+        let mut claims = valid_claims();
+        claims["iss"] = json!("https://evil.example.com");
+        let result = check(Some(token(claims))).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[actix_rt::test]
+    async fn must_login_without_token() {
+        let result = check(None).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[actix_rt::test]
+    async fn denied_without_user_claim() {
+        let mut claims = valid_claims();
+        claims.as_object_mut().unwrap().remove("sub");
+        let result = check(Some(token(claims))).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn audience_and_issuer_are_required() {
+        let mut without_audience = config();
+        without_audience.audience.clear();
+        assert!(JwtFactory::from_config(&without_audience).await.is_err());
+        let mut without_issuer = config();
+        without_issuer.issuer.clear();
+        assert!(JwtFactory::from_config(&without_issuer).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn must_login_without_audience() {
+        let mut claims = valid_claims();
+        claims.as_object_mut().unwrap().remove("aud");
+        let result = check(Some(token(claims))).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[test]
+    fn source_must_be_unique() {
+        let mut config = config();
+        config.jwks_url = Some("https://issuer.example.com/jwks".into());
+        assert!(JwksSource::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,544 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::header::HeaderValue;
+use actix_web::http::header::WWW_AUTHENTICATE;
+use actix_web::HttpRequest;
+use anyhow::Result;
+use ldap3::Ldap;
+use ldap3::LdapConnAsync;
+use ldap3::LdapConnSettings;
+use ldap3::Scope;
+use ldap3::SearchEntry;
+
+use crate::authenticator::cache::TtlCache;
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::LdapConfig;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// LDAP result code returned when bind credentials are not valid.
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Directory user matching the credentials provided by clients.
+#[derive(Clone, Debug)]
+struct LdapUser {
+    dn: String,
+    user: String,
+}
+
+/// Open a connection to the directory with the configured options.
+pub async fn connect(url: &str, starttls: bool, timeout: Duration) -> Result<Ldap> {
+    let settings = LdapConnSettings::new()
+        .set_conn_timeout(timeout)
+        .set_starttls(starttls);
+    let (conn, ldap) = LdapConnAsync::with_settings(settings, url).await?;
+    ldap3::drive!(conn);
+    Ok(ldap)
+}
+
+/// Build a search filter replacing placeholders with escaped values.
+///
+/// All placeholders are replaced in a single pass so values are never substituted again.
+pub fn search_filter(filter: &str, values: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(filter.len());
+    let mut rest = filter;
+    while !rest.is_empty() {
+        match values
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                result.push_str(&ldap3::ldap_escape(*value));
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                let next = rest.chars().next().unwrap();
+                result.push(next);
+                rest = &rest[next.len_utf8()..];
+            }
+        }
+    }
+    result
+}
+
+/// Build the search filter for the given username.
+pub fn user_filter(filter: &str, user: &str) -> String {
+    search_filter(filter, &[("{user}", user)])
+}
+
+/// Authenticate users with HTTP Basic credentials by searching and binding to an LDAP directory.
+pub struct LdapBind {
+    cache: Arc<TtlCache<LdapUser>>,
+    challenge: HeaderValue,
+    config: LdapConfig,
+}
+
+impl LdapBind {
+    /// Ask clients to provide (new) credentials.
+    fn must_login(&self) -> AuthenticationResult {
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::MustLogin);
+        result
+            .headers
+            .insert(WWW_AUTHENTICATE, self.challenge.clone());
+        result
+    }
+
+    /// Find the user in the directory and bind as them to verify the password.
+    ///
+    /// Returns `None` if the user does not exist or the password is not valid.
+    async fn bind(&self, ldap: &mut Ldap, user: &str, password: &str) -> Result<Option<LdapUser>> {
+        let timeout = Duration::from_secs(self.config.timeout_sec);
+        let bind_dn = self.config.bind_dn.as_deref().unwrap_or("");
+        let bind_password = self.config.bind_password.as_deref().unwrap_or("");
+        ldap.with_timeout(timeout)
+            .simple_bind(bind_dn, bind_password)
+            .await?
+            .success()?;
+
+        // Search for the user entry.
+        let filter = user_filter(&self.config.filter, user);
+        let attributes = vec![self.config.user_attribute.as_str()];
+        let (entries, _) = ldap
+            .with_timeout(timeout)
+            .search(&self.config.base_dn, Scope::Subtree, &filter, attributes)
+            .await?
+            .success()?;
+        let entry = match entries.len() {
+            0 => {
+                log::debug!("LDAP user '{}' not found", user);
+                return Ok(None);
+            }
+            1 => SearchEntry::construct(entries.into_iter().next().unwrap()),
+            count => anyhow::bail!("found {} LDAP entries for user '{}'", count, user),
+        };
+
+        // Verify the password by binding as the user.
+        let bind = ldap
+            .with_timeout(timeout)
+            .simple_bind(&entry.dn, password)
+            .await?;
+        if bind.rc == INVALID_CREDENTIALS {
+            log::debug!("Invalid password for LDAP user '{}'", user);
+            return Ok(None);
+        }
+        bind.success()?;
+
+        let user = entry
+            .attrs
+            .get(&self.config.user_attribute)
+            .and_then(|values| values.first())
+            .cloned()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "LDAP entry {} has no '{}' attribute for the user ID",
+                    entry.dn,
+                    self.config.user_attribute
+                )
+            })?;
+        Ok(Some(LdapUser { dn: entry.dn, user }))
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for LdapBind {
+    async fn check(
+        &self,
+        _: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let credentials = match super::credentials::extract_basic(request) {
+            None => return Ok(self.must_login()),
+            Some(credentials) => credentials,
+        };
+        // Empty passwords are unauthenticated binds that most directories accept.
+        if credentials.password.is_empty() {
+            return Ok(self.must_login());
+        }
+
+        // Check credentials with the directory unless they were recently verified.
+        // Any errors interacting with the directory is logged and requests are denied.
+        let key = crate::authenticator::hash_token(&format!(
+            "{}:{}",
+            credentials.user, credentials.password
+        ));
+        let found = match self.cache.get(&key) {
+            Some(found) => found,
+            None => {
+                let timeout = Duration::from_secs(self.config.timeout_sec);
+                let bind = match connect(&self.config.url, self.config.starttls, timeout).await {
+                    Err(error) => Err(error),
+                    Ok(mut ldap) => {
+                        let bind = self
+                            .bind(&mut ldap, &credentials.user, &credentials.password)
+                            .await;
+                        let _ = ldap.unbind().await;
+                        bind
+                    }
+                };
+                match bind {
+                    Ok(None) => return Ok(self.must_login()),
+                    Ok(Some(found)) => {
+                        self.cache.insert(key, found.clone());
+                        found
+                    }
+                    Err(error) => {
+                        log::error!("Unable to check credentials with LDAP: {:?}", error);
+                        return Ok(AuthenticationResult::denied());
+                    }
+                }
+            }
+        };
+
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        let context = &mut result.authentication_context;
+        context.authenticated = true;
+        context.user = Some(found.user);
+        context.attributes.insert("dn".into(), found.dn);
+        Ok(result)
+    }
+}
+
+/// Instantiate per-thread `LdapBind` instances sharing the same cache of verified credentials.
+pub struct LdapBindFactory {
+    cache: Arc<TtlCache<LdapUser>>,
+    challenge: HeaderValue,
+    config: LdapConfig,
+}
+
+impl LdapBindFactory {
+    pub fn from_config(config: &LdapConfig) -> Result<LdapBindFactory> {
+        if !config.filter.contains("{user}") {
+            anyhow::bail!("LDAP filter must include the `{{user}}` placeholder");
+        }
+        let challenge = super::credentials::basic_challenge(&config.realm)?;
+        let cache = TtlCache::new(Duration::from_secs(config.cache_ttl_sec));
+        Ok(LdapBindFactory {
+            cache: Arc::new(cache),
+            challenge,
+            config: config.clone(),
+        })
+    }
+}
+
+impl AuthenticationProxyFactory for LdapBindFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        Box::new(LdapBind {
+            cache: Arc::clone(&self.cache),
+            challenge: self.challenge.clone(),
+            config: self.config.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::SocketAddr;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use actix_web::test::TestRequest;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use lber::common::TagClass;
+    use lber::structure::StructureTag;
+
+    use super::LdapBindFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::LdapConfig;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    /// Users in the mock directory as (DN, uid, password) tuples.
+    const USERS: &[(&str, &str, &str)] = &[
+        ("cn=admin,dc=example,dc=com", "admin", "admin-password"),
+        (
+            "uid=alice,ou=people,dc=example,dc=com",
+            "alice",
+            "alice-password",
+        ),
+        ("uid=bob,ou=people,dc=example,dc=com", "bob", "bob-password"),
+    ];
+
+    /// Groups in the mock directory as (DN, member uids) tuples.
+    const GROUPS: &[(&str, &[&str])] = &[
+        ("cn=dev,ou=groups,dc=example,dc=com", &["alice", "bob"]),
+        ("cn=SRE,ou=groups,dc=example,dc=com", &["alice"]),
+        ("ou=groups,dc=example,dc=com", &["alice"]),
+    ];
+
+    /// BER encode a tag with the given content.
+    fn ber(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![tag];
+        if content.len() < 128 {
+            encoded.push(content.len() as u8);
+        } else {
+            let length = (content.len() as u32).to_be_bytes();
+            let length: Vec<u8> = length.iter().copied().skip_while(|b| *b == 0).collect();
+            encoded.push(0x80 | length.len() as u8);
+            encoded.extend(length);
+        }
+        encoded.extend(content);
+        encoded
+    }
+
+    fn primitive(tag: StructureTag) -> Vec<u8> {
+        tag.expect_primitive().unwrap_or_default()
+    }
+
+    fn ldap_result(operation: u8, code: u8) -> Vec<u8> {
+        let result = [ber(0x0a, &[code]), ber(0x04, b""), ber(0x04, b"")].concat();
+        ber(0x60 | operation, &result)
+    }
+
+    /// DNs of the groups the user is a member of.
+    fn member_of(uid: &str) -> Vec<&'static str> {
+        GROUPS
+            .iter()
+            .filter(|(_, members)| members.contains(&uid))
+            .map(|(dn, _)| *dn)
+            .collect()
+    }
+
+    /// Find the entries searched for by an `(uid=value)` or `(member=dn)` filter.
+    fn search(filter: StructureTag) -> Vec<Vec<u8>> {
+        if filter.class != TagClass::Context || filter.id != 3 {
+            return Vec::new();
+        }
+        let mut parts = filter.expect_constructed().unwrap_or_default().into_iter();
+        let (attribute, value) = match (parts.next(), parts.next()) {
+            (Some(attribute), Some(value)) => (primitive(attribute), primitive(value)),
+            _ => return Vec::new(),
+        };
+        match attribute.as_slice() {
+            b"uid" => USERS
+                .iter()
+                .filter(|(_, uid, _)| uid.as_bytes() == value.as_slice())
+                .map(|(dn, uid, _)| {
+                    let member_of = member_of(uid);
+                    search_entry(dn, &[("uid", &[uid]), ("memberOf", &member_of)])
+                })
+                .collect(),
+            b"member" => USERS
+                .iter()
+                .filter(|(dn, _, _)| dn.as_bytes() == value.as_slice())
+                .flat_map(|(_, uid, _)| member_of(uid))
+                .map(|dn| search_entry(dn, &[]))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn search_entry(dn: &str, attributes: &[(&str, &[&str])]) -> Vec<u8> {
+        let attributes: Vec<u8> = attributes
+            .iter()
+            .flat_map(|(name, values)| {
+                let values: Vec<u8> = values
+                    .iter()
+                    .flat_map(|value| ber(0x04, value.as_bytes()))
+                    .collect();
+                ber(
+                    0x30,
+                    &[ber(0x04, name.as_bytes()), ber(0x31, &values)].concat(),
+                )
+            })
+            .collect();
+        let attributes = ber(0x30, &attributes);
+        ber(0x64, &[ber(0x04, dn.as_bytes()), attributes].concat())
+    }
+
+    /// Respond to an LDAP message, returning false when the client unbinds.
+    fn respond(stream: &mut TcpStream, message: StructureTag, binds: &AtomicUsize) -> bool {
+        let mut parts = match message.expect_constructed() {
+            Some(parts) if parts.len() >= 2 => parts.into_iter(),
+            _ => return false,
+        };
+        let id = ber(0x02, &primitive(parts.next().unwrap()));
+        let operation = parts.next().unwrap();
+        let mut responses = Vec::new();
+        match (operation.class, operation.id) {
+            // Bind request.
+            (TagClass::Application, 0) => {
+                binds.fetch_add(1, Ordering::SeqCst);
+                let mut fields = operation.expect_constructed().unwrap().into_iter().skip(1);
+                let dn = primitive(fields.next().unwrap());
+                let password = primitive(fields.next().unwrap());
+                let valid = dn.is_empty()
+                    || USERS.iter().any(|(user_dn, _, user_password)| {
+                        user_dn.as_bytes() == dn.as_slice()
+                            && user_password.as_bytes() == password.as_slice()
+                    });
+                responses.push(ldap_result(1, if valid { 0 } else { 49 }));
+            }
+            // Search request.
+            (TagClass::Application, 3) => {
+                let filter = operation
+                    .expect_constructed()
+                    .unwrap()
+                    .into_iter()
+                    .nth(6)
+                    .unwrap();
+                responses.extend(search(filter));
+                responses.push(ldap_result(5, 0));
+            }
+            _ => return false,
+        }
+        for response in responses {
+            let message = ber(0x30, &[id.clone(), response].concat());
+            if stream.write_all(&message).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn serve(mut stream: TcpStream, binds: Arc<AtomicUsize>) {
+        let mut buffer = Vec::new();
+        let mut chunk = [0; 4096];
+        loop {
+            match lber::parse::parse_tag(&buffer) {
+                Ok((rest, message)) => {
+                    let consumed = buffer.len() - rest.len();
+                    buffer.drain(..consumed);
+                    if !respond(&mut stream, message, &binds) {
+                        return;
+                    }
+                }
+                Err(_) => match stream.read(&mut chunk) {
+                    Ok(0) | Err(_) => return,
+                    Ok(read) => buffer.extend(&chunk[..read]),
+                },
+            }
+        }
+    }
+
+    /// Start a minimal LDAP directory with test users and return its address and bind counter.
+    pub fn mock_directory() -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let binds = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&binds);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let binds = Arc::clone(&counter);
+                std::thread::spawn(move || serve(stream, binds));
+            }
+        });
+        (address, binds)
+    }
+
+    fn factory(address: SocketAddr, cache_ttl_sec: u64) -> LdapBindFactory {
+        let config: LdapConfig = serde_yaml::from_str(&format!(
+            r#"
+            base-dn: 'dc=example,dc=com'
+            bind-dn: 'cn=admin,dc=example,dc=com'
+            bind-password: admin-password
+            cache-ttl-sec: {}
+            url: 'ldap://{}'
+            "#,
+            cache_ttl_sec, address
+        ))
+        .unwrap();
+        LdapBindFactory::from_config(&config).unwrap()
+    }
+
+    async fn check(factory: &LdapBindFactory, credentials: Option<&str>) -> AuthenticationResult {
+        let mut request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        if let Some(credentials) = credentials {
+            let credentials = format!("Basic {}", BASE64.encode(credentials));
+            request = request.append_header(("Authorization", credentials));
+        }
+        let request = request.to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        factory.make().check(&context, &request).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn bind_allowed() {
+        let (address, _) = mock_directory();
+        let result = check(&factory(address, 30), Some("alice:alice-password")).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        let context = &result.authentication_context;
+        assert!(context.authenticated);
+        assert_eq!(context.user, Some("alice".into()));
+        assert_eq!(
+            context.attributes["dn"],
+            "uid=alice,ou=people,dc=example,dc=com"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn bind_cached() {
+        let (address, binds) = mock_directory();
+        let factory = factory(address, 30);
+        check(&factory, Some("alice:alice-password")).await;
+        let result = check(&factory, Some("alice:alice-password")).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        // Service account and user binds for the first request only.
+        assert_eq!(binds.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_rt::test]
+    async fn bind_invalid_password() {
+        let (address, binds) = mock_directory();
+        let factory = factory(address, 30);
+        let result = check(&factory, Some("alice:wrong")).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert!(result.headers.contains_key("www-authenticate"));
+        // Failed binds are not cached.
+        check(&factory, Some("alice:wrong")).await;
+        assert_eq!(binds.load(Ordering::SeqCst), 4);
+    }
+
+    #[actix_rt::test]
+    async fn bind_unknown_user() {
+        let (address, _) = mock_directory();
+        let result = check(&factory(address, 30), Some("mallory:password")).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[actix_rt::test]
+    async fn directory_unavailable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        let result = check(&factory(address, 30), Some("alice:alice-password")).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn empty_password() {
+        let (address, binds) = mock_directory();
+        let result = check(&factory(address, 30), Some("alice:")).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert_eq!(binds.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn filter_substitutes_once() {
+        let filter = super::search_filter(
+            "(|(member={dn})(memberUid={user}))",
+            &[("{dn}", "uid=x{user},dc=example"), ("{user}", "{dn}")],
+        );
+        assert_eq!(filter, "(|(member=uid=x{user},dc=example)(memberUid={dn}))");
+    }
+
+    #[test]
+    fn filter_escapes_user() {
+        let filter = super::user_filter("(&(objectClass=person)(uid={user}))", "a*)(uid=b");
+        assert_eq!(filter, "(&(objectClass=person)(uid=a\\2a\\29\\28uid=b))");
+    }
+}
@@ -1,9 +1,15 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use actix_web::HttpRequest;
+use anyhow::Context;
 use anyhow::Result;
+use sha3::Digest;
+use sha3::Sha3_512 as Sha512;
 
 use crate::config::AuthenticatorBackend;
+use crate::config::AuthenticatorConfig;
+use crate::config::AuthenticatorSelection;
 use crate::config::Config;
 use crate::engine::RulesEngine;
 use crate::models::AuditReason;
@@ -13,13 +19,36 @@ use crate::models::RequestContext;
 use crate::models::RuleAction;
 
 mod allow_all;
+mod api_key;
+mod cache;
+mod chain;
+mod client_cert;
+mod credentials;
+mod forward_auth;
+mod htpasswd;
+mod http;
 mod identity_headers;
+mod introspection;
+mod jwt;
+mod ldap;
 mod oauth2_proxy;
+mod roles;
+mod trusted_header;
 
 #[cfg(test)]
 pub mod tests;
 
 use self::identity_headers::IdentityHeaders;
+use self::roles::RolesLookup;
+use self::roles::RolesLookupFactory;
+
+/// One-way hash a secret token into an opaque ID that is safe to store.
+///
+/// The same token always results in the same ID so they can be used as session IDs.
+pub fn hash_token(token: &str) -> String {
+    let digest = Sha512::digest(token.as_bytes());
+    format!("{:X}", digest)
+}
 
 /// Interface to authentication implementations.
 #[async_trait::async_trait(?Send)]
@@ -41,55 +70,179 @@ pub trait AuthenticationProxyFactory: Send + Sync {
     fn make(&self) -> Box<dyn AuthenticationProxy>;
 }
 
+/// Create an `AuthenticationProxyFactory` for the configured backend.
+async fn backend_factory(
+    backend: &AuthenticatorBackend,
+) -> Result<Arc<dyn AuthenticationProxyFactory>> {
+    let factory: Arc<dyn AuthenticationProxyFactory> = match backend {
+        #[cfg(debug_assertions)]
+        AuthenticatorBackend::AllowAll => Arc::new(self::allow_all::AllowAll {}),
+        AuthenticatorBackend::ApiKey(api_key) => {
+            Arc::new(self::api_key::ApiKeyFactory::from_config(api_key)?)
+        }
+        AuthenticatorBackend::ClientCert(client_cert) => Arc::new(
+            self::client_cert::ClientCertFactory::from_config(client_cert)?,
+        ),
+        AuthenticatorBackend::ForwardAuth(forward_auth) => Arc::new(
+            self::forward_auth::ForwardAuthFactory::from_config(forward_auth)?,
+        ),
+        AuthenticatorBackend::Htpasswd(htpasswd) => {
+            Arc::new(self::htpasswd::HtpasswdFactory::from_config(htpasswd)?)
+        }
+        AuthenticatorBackend::Introspection(introspection) => Arc::new(
+            self::introspection::IntrospectionFactory::from_config(introspection),
+        ),
+        AuthenticatorBackend::Jwt(jwt) => Arc::new(self::jwt::JwtFactory::from_config(jwt).await?),
+        AuthenticatorBackend::Ldap(ldap) => {
+            Arc::new(self::ldap::LdapBindFactory::from_config(ldap)?)
+        }
+        AuthenticatorBackend::OAuth2Proxy(oauth2_proxy) => Arc::new(
+            self::oauth2_proxy::OAuth2ProxyFactory::from_config(oauth2_proxy)?,
+        ),
+        AuthenticatorBackend::TrustedHeader(trusted_header) => Arc::new(
+            self::trusted_header::TrustedHeaderFactory::from_config(trusted_header)?,
+        ),
+    };
+    Ok(factory)
+}
+
 /// Wrap logic around authentication proxy and rules engine.
 pub struct Authenticator {
-    /// Headers to inject user identity information into.
-    pub headers: IdentityHeaders,
+    /// Headers to inject user identity information into, by authenticator name.
+    headers: Arc<HashMap<String, IdentityHeaders>>,
 
-    /// The Authenticator proxy to check requests with.
+    /// Named authenticator proxies that can be selected to check requests with.
+    named: HashMap<String, Box<dyn AuthenticationProxy>>,
+
+    /// The default Authenticator proxy to check requests with.
     proxy: Box<dyn AuthenticationProxy>,
 
+    /// Lookup the roles of authenticated users.
+    roles: Box<dyn RolesLookup>,
+
     /// Rules engine to customise and enrich the authentication process.
     rules: RulesEngine,
+
+    /// Conditions to select named authenticator proxies for requests.
+    selection: Arc<Vec<AuthenticatorSelection>>,
 }
 
 impl Authenticator {
     /// Create an AuthenticatorFactory from configuration options.
-    pub fn factory(config: &Config) -> Result<AuthenticatorFactory> {
-        let factory: Arc<dyn AuthenticationProxyFactory> = match config.authenticator.backend {
-            #[cfg(debug_assertions)]
-            AuthenticatorBackend::AllowAll => Arc::new(self::allow_all::AllowAll {}),
-            AuthenticatorBackend::OAuth2Proxy(ref oauth2_proxy) => Arc::new(
-                self::oauth2_proxy::OAuth2ProxyFactory::from_config(oauth2_proxy),
-            ),
-        };
-        let headers = IdentityHeaders::from_config(&config.authenticator)?;
+    pub async fn factory(config: &Config) -> Result<AuthenticatorFactory> {
+        let mut headers = HashMap::new();
+        let factory = Authenticator::chain_factory(&config.authenticator.0, &mut headers).await?;
+        let mut named = HashMap::new();
+        for (name, chain) in &config.authenticators {
+            let factory = Authenticator::chain_factory(&chain.0, &mut headers)
+                .await
+                .with_context(|| format!("Unable to configure authenticator '{}'", name))?;
+            named.insert(name.clone(), factory);
+        }
+        for selection in &config.authenticator_selection {
+            if !named.contains_key(&selection.authenticator) {
+                anyhow::bail!(
+                    "selected authenticator '{}' is not configured",
+                    selection.authenticator
+                );
+            }
+        }
+        let roles =
+            self::roles::factory(&config.roles).context("Unable to configure roles lookup")?;
         let rules = RulesEngine::builder()
             .rule_files(&config.rule_files)
             .build()?;
         Ok(AuthenticatorFactory {
             factory,
-            headers,
+            headers: Arc::new(headers),
+            named: Arc::new(named),
+            roles,
             rules,
+            selection: Arc::new(config.authenticator_selection.clone()),
         })
     }
 
+    /// Create a factory for a chain of authenticators and collect their identity headers.
+    ///
+    /// Authenticator names must be unique across all chains.
+    async fn chain_factory(
+        configs: &[AuthenticatorConfig],
+        headers: &mut HashMap<String, IdentityHeaders>,
+    ) -> Result<Arc<dyn AuthenticationProxyFactory>> {
+        if configs.is_empty() {
+            anyhow::bail!("at least one authenticator must be configured");
+        }
+        let mut links = Vec::new();
+        for config in configs {
+            let name = config.name().to_string();
+            let identity = IdentityHeaders::from_config(config)?;
+            if headers.insert(name.clone(), identity).is_some() {
+                anyhow::bail!(
+                    "authenticator name '{}' is used more than once, set a unique `name`",
+                    name
+                );
+            }
+            let factory = backend_factory(&config.backend).await?;
+            links.push((name, factory));
+        }
+        Ok(Arc::new(self::chain::ChainFactory::new(links)))
+    }
+
     /// Instantiate an authenticator from the given authentication proxy.
     #[cfg(test)]
     pub fn from<A>(authenticator: A) -> Authenticator
     where
         A: AuthenticationProxy + 'static,
     {
-        let headers = IdentityHeaders::default();
+        let headers = Arc::new(HashMap::new());
         let rules = RulesEngine::builder().build().unwrap();
         let proxy = Box::new(authenticator);
         Authenticator {
             headers,
+            named: HashMap::new(),
             proxy,
+            roles: Box::new(self::roles::NoopRoles {}),
             rules,
+            selection: Arc::new(Vec::new()),
         }
     }
 
+    /// Check requests matching the conditions with the given authentication proxy.
+    #[cfg(test)]
+    pub fn select<A>(mut self, matches: crate::models::RuleMatches, authenticator: A) -> Self
+    where
+        A: AuthenticationProxy + 'static,
+    {
+        let name = format!("selected-{}", self.selection.len());
+        let selection = AuthenticatorSelection {
+            authenticator: name.clone(),
+            matches,
+        };
+        self.named.insert(name, Box::new(authenticator));
+        Arc::make_mut(&mut self.selection).push(selection);
+        self
+    }
+
+    /// Lookup the roles of authenticated users with the given roles lookup.
+    #[cfg(test)]
+    pub fn with_roles<R>(mut self, roles: R) -> Self
+    where
+        R: RolesLookup + 'static,
+    {
+        self.roles = Box::new(roles);
+        self
+    }
+
+    /// Headers to inject user identity information from the authentication result into.
+    pub fn identity_headers(&self, result: &AuthenticationResult) -> IdentityHeaders {
+        result
+            .authenticator
+            .as_ref()
+            .and_then(|name| self.headers.get(name))
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Check a request for valid authentication.
     pub async fn check(
         &self,
@@ -112,11 +265,25 @@ impl Authenticator {
         };
 
         // Authenticate against the AuthProxy, directing users to login if needed.
-        let mut result = self.proxy.check(context, request).await?;
+        let mut result = self.select_proxy(context).check(context, request).await?;
         if let AuthenticationStatus::MustLogin = result.status {
             return Ok(result);
         }
 
+        // Lookup the roles of identified users so rules can use them.
+        // Errors looking up roles are logged and requests are denied.
+        if let Some(user) = result.authentication_context.user.clone() {
+            match self.roles.lookup(&user).await {
+                Ok(roles) => result.authentication_context.roles.extend(roles),
+                Err(error) => {
+                    log::error!("Unable to lookup roles for user '{}': {:?}", user, error);
+                    result.audit_reason = AuditReason::RolesLookupFailed;
+                    result.status = AuthenticationStatus::Denied;
+                    return Ok(result);
+                }
+            }
+        }
+
         // Process post-authentication rules.
         let postauth = self
             .rules
@@ -136,6 +303,16 @@ impl Authenticator {
         // Process enrich rules for allowed responses.
         self.rules.eval_enrich(context, result)
     }
+
+    /// Select the authentication proxy to check the request with.
+    fn select_proxy(&self, context: &RequestContext) -> &dyn AuthenticationProxy {
+        self.selection
+            .iter()
+            .find(|selection| selection.matches.check(context))
+            .and_then(|selection| self.named.get(&selection.authenticator))
+            .map(|proxy| proxy.as_ref())
+            .unwrap_or_else(|| self.proxy.as_ref())
+    }
 }
 
 /// Thread-safe logic to create thread-scoped `Authenticator` instances.
@@ -145,17 +322,27 @@ impl Authenticator {
 #[derive(Clone)]
 pub struct AuthenticatorFactory {
     factory: Arc<dyn AuthenticationProxyFactory>,
-    headers: IdentityHeaders,
+    headers: Arc<HashMap<String, IdentityHeaders>>,
+    named: Arc<HashMap<String, Arc<dyn AuthenticationProxyFactory>>>,
+    roles: Arc<dyn RolesLookupFactory>,
     rules: RulesEngine,
+    selection: Arc<Vec<AuthenticatorSelection>>,
 }
 
 impl AuthenticatorFactory {
     /// Return a new `Authenticator` instance.
     pub fn make(&self) -> Authenticator {
         Authenticator {
-            headers: self.headers.clone(),
+            headers: Arc::clone(&self.headers),
+            named: self
+                .named
+                .iter()
+                .map(|(name, factory)| (name.clone(), factory.make()))
+                .collect(),
             proxy: self.factory.make(),
+            roles: self.roles.make(),
             rules: self.rules.clone(),
+            selection: Arc::clone(&self.selection),
         }
     }
 }
@@ -1,12 +1,17 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
 use actix_web::http::header::HeaderMap;
+use actix_web::http::header::HeaderName;
 use actix_web::HttpRequest;
 use anyhow::Result;
 use awc::Client;
-use sha3::Digest;
-use sha3::Sha3_512 as Sha512;
 
+use crate::authenticator::http::copy_headers;
+use crate::authenticator::http::extract_header;
+use crate::authenticator::http::extract_list;
+use crate::authenticator::http::forward;
+use crate::authenticator::http::parse_header_names;
 use crate::authenticator::AuthenticationProxy;
 use crate::authenticator::AuthenticationProxyFactory;
 use crate::config::OAuth2ProxyConfig;
@@ -18,27 +23,25 @@ use crate::models::RequestContext;
 
 const ACCESSS_TOKEN_HEADER: &str = "x-auth-request-access-token";
 const USER_EMAIL_HEADER: &str = "x-auth-request-email";
+const USER_GROUPS_HEADER: &str = "x-auth-request-groups";
 const USER_ID_HEADER: &str = "x-auth-request-user";
-
-/// Attempt to extract the named header from response headers.
-fn extract_header(headers: &HeaderMap, header: &str) -> Option<String> {
-    match headers.get(header) {
-        None => None,
-        Some(value) => match String::from_utf8(value.as_bytes().to_vec()) {
-            Ok(value) => Some(value),
-            Err(error) => {
-                log::error!("Unable to UTF8 decode header `{}`: {:?}", header, error);
-                None
-            }
-        },
-    }
-}
+const USER_PREFERRED_USERNAME_HEADER: &str = "x-auth-request-preferred-username";
 
 /// Attempt to extract the user email from response headers.
 fn extract_email(headers: &HeaderMap) -> Option<String> {
     extract_header(headers, USER_EMAIL_HEADER)
 }
 
+/// Attempt to extract the user groups from response headers.
+fn extract_groups(headers: &HeaderMap) -> Vec<String> {
+    extract_list(headers, USER_GROUPS_HEADER, ",")
+}
+
+/// Attempt to extract the user preferred username from response headers.
+fn extract_preferred_username(headers: &HeaderMap) -> Option<String> {
+    extract_header(headers, USER_PREFERRED_USERNAME_HEADER)
+}
+
 /// Attempt to extract the user ID from response headers.
 fn extract_user(headers: &HeaderMap) -> Option<String> {
     extract_header(headers, USER_ID_HEADER)
@@ -54,21 +57,23 @@ fn extract_session(headers: &HeaderMap) -> Option<String> {
     // - Consistently "corrupt" tokens so one token never becomes two.
 
     // One-way encrypt the Authentication token to derive a session ID.
-    session.map(|session| {
-        let session = Sha512::digest(session.as_bytes());
-        format!("{:X}", session)
-    })
+    session.map(|session| crate::authenticator::hash_token(&session))
 }
 
 /// Authenticate users with [oauth2_proxy](https://oauth2-proxy.github.io/oauth2-proxy/).
 pub struct OAuth2Proxy {
     client: Client,
     config: OAuth2ProxyConfig,
+    response_headers: Vec<HeaderName>,
+    statuses: HashMap<u16, AuthenticationStatus>,
 }
 
 impl OAuth2Proxy {
     /// Return an OAuth2Proxy backend from the configuration.
-    pub fn from_config(config: &OAuth2ProxyConfig) -> OAuth2Proxy {
+    pub fn from_config(
+        config: &OAuth2ProxyConfig,
+        response_headers: Vec<HeaderName>,
+    ) -> OAuth2Proxy {
         let mut config = config.clone();
         if config.address.ends_with('/') {
             config.address = config.address.trim_end_matches('/').to_string();
@@ -83,7 +88,15 @@ impl OAuth2Proxy {
             .disable_redirects()
             .timeout(Duration::from_secs(config.timeout_sec))
             .finish();
-        OAuth2Proxy { client, config }
+        let mut statuses = HashMap::new();
+        statuses.insert(202, AuthenticationStatus::Allowed);
+        statuses.insert(401, AuthenticationStatus::MustLogin);
+        OAuth2Proxy {
+            client,
+            config,
+            response_headers,
+            statuses,
+        }
     }
 }
 
@@ -109,44 +122,38 @@ impl AuthenticationProxy for OAuth2Proxy {
         // Proxy request up to OAuth2Proxy.
         // Any errors interacting with OAuth2Proxy is logged and user requests are denied.
         let url = format!("{}{}auth", self.config.address, self.config.prefix);
-        let response = self.client.request_from(url, request.head()).send().await;
-        let response = match response {
-            Ok(response) => response,
-            Err(error) => {
-                log::error!("Unable to check request with OAuth2Proxy: {:?}", error);
-                return Ok(AuthenticationResult::denied());
-            }
-        };
-
-        // Validate response for HTTP-level errors (500, 404, etc ...).
-        let status = match response.status().as_u16() {
-            202 => AuthenticationStatus::Allowed,
-            401 => AuthenticationStatus::MustLogin,
-            _ => {
-                let mut response = response;
-                let body = response.body().await;
-                log::error!(
-                    "Unexpected status code from OAuth2Proxy: {}",
-                    response.status()
-                );
-                log::debug!("Response body for unexpected status code: {:?}", body);
-                return Ok(AuthenticationResult::denied());
-            }
+        let response = forward(&self.client, &url, request, &self.statuses, "OAuth2Proxy").await;
+        let (status, headers) = match response {
+            Some(response) => response,
+            None => return Ok(AuthenticationResult::denied()),
         };
 
         // Extract user information from oauth2_proxy response.
-        let headers = response.headers();
+        let email = extract_email(&headers);
+        let preferred_username = extract_preferred_username(&headers);
         let user = match self.config.user_id_source_header {
-            OAuth2ProxyUserIdSourceHeader::Email => extract_email(headers),
-            OAuth2ProxyUserIdSourceHeader::User => extract_user(headers),
+            OAuth2ProxyUserIdSourceHeader::Email => email.clone(),
+            OAuth2ProxyUserIdSourceHeader::PreferredUsername => preferred_username.clone(),
+            OAuth2ProxyUserIdSourceHeader::User => extract_user(&headers),
         };
-        let session = extract_session(headers);
+        let session = extract_session(&headers);
 
         // Return generated authentication result and context.
         let mut result = AuthenticationResult::from_status(status);
-        result.authentication_context.authenticated = status.authenticated();
-        result.authentication_context.user = user;
-        result.authentication_context.session = session;
+        let context = &mut result.authentication_context;
+        context.authenticated = status.authenticated();
+        context.user = user;
+        context.session = session;
+        context.groups = extract_groups(&headers);
+        if let Some(email) = email {
+            context.attributes.insert("email".into(), email);
+        }
+        if let Some(preferred_username) = preferred_username {
+            context
+                .attributes
+                .insert("preferred_username".into(), preferred_username);
+        }
+        copy_headers(&headers, &self.response_headers, &mut result.headers);
         Ok(result)
     }
 }
@@ -154,17 +161,128 @@ impl AuthenticationProxy for OAuth2Proxy {
 /// Instantiate per-thread `OAuth2Proxy` instances.
 pub struct OAuth2ProxyFactory {
     config: OAuth2ProxyConfig,
+    response_headers: Vec<HeaderName>,
 }
 
 impl OAuth2ProxyFactory {
-    pub fn from_config(config: &OAuth2ProxyConfig) -> OAuth2ProxyFactory {
-        let config = config.clone();
-        OAuth2ProxyFactory { config }
+    pub fn from_config(config: &OAuth2ProxyConfig) -> Result<OAuth2ProxyFactory> {
+        let response_headers = parse_header_names(&config.response_headers)?;
+        Ok(OAuth2ProxyFactory {
+            config: config.clone(),
+            response_headers,
+        })
     }
 }
 
 impl AuthenticationProxyFactory for OAuth2ProxyFactory {
     fn make(&self) -> Box<dyn AuthenticationProxy> {
-        Box::new(OAuth2Proxy::from_config(&self.config))
+        Box::new(OAuth2Proxy::from_config(
+            &self.config,
+            self.response_headers.clone(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+
+    use super::OAuth2ProxyFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::OAuth2ProxyConfig;
+    use crate::config::RequestExtraction;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    async fn auth(request: HttpRequest) -> HttpResponse {
+        let cookie = request
+            .headers()
+            .get("cookie")
+            .and_then(|cookie| cookie.to_str().ok());
+        match cookie {
+            Some("_oauth2_proxy=alice") => HttpResponse::Accepted()
+                .append_header(("X-Auth-Request-User", "alice"))
+                .append_header(("X-Auth-Request-Email", "alice@example.com"))
+                .append_header(("X-Auth-Request-Groups", "admins,role:sre"))
+                .append_header(("X-Auth-Request-Preferred-Username", "Alice"))
+                .append_header(("X-Auth-Request-Access-Token", "token"))
+                .append_header(("Set-Cookie", "_oauth2_proxy=refreshed; Path=/"))
+                .append_header(("Set-Cookie", "_oauth2_proxy_1=part; Path=/"))
+                .finish(),
+            _ => HttpResponse::Unauthorized().finish(),
+        }
+    }
+
+    fn configure(app: &mut web::ServiceConfig) {
+        app.route("/oauth2/auth", web::get().to(auth));
+    }
+
+    async fn check(cookie: Option<&str>) -> AuthenticationResult {
+        check_with(cookie, "").await
+    }
+
+    async fn check_with(cookie: Option<&str>, config: &str) -> AuthenticationResult {
+        let (address, server) = crate::authenticator::tests::mock_server(configure);
+        let config = format!("address: 'http://{}'\n{}", address, config);
+        let config: OAuth2ProxyConfig = serde_yaml::from_str(&config).unwrap();
+        let factory = OAuth2ProxyFactory::from_config(&config).unwrap();
+        let mut request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        if let Some(cookie) = cookie {
+            request = request.append_header(("Cookie", cookie));
+        }
+        let request = request.to_http_request();
+        let context =
+            RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+        let result = factory.make().check(&context, &request).await.unwrap();
+        server.stop(false).await;
+        result
+    }
+
+    #[actix_rt::test]
+    async fn allowed() {
+        let result = check(Some("_oauth2_proxy=alice")).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        let context = &result.authentication_context;
+        assert!(context.authenticated);
+        assert_eq!(context.user, Some("alice".into()));
+        assert!(context.session.is_some());
+        assert_eq!(context.attributes["email"], "alice@example.com");
+        assert_eq!(context.attributes["preferred_username"], "Alice");
+        assert_eq!(context.groups, vec!["admins", "role:sre"]);
+    }
+
+    #[actix_rt::test]
+    async fn user_from_preferred_username() {
+        let config = "user-id-source-header: preferred-username";
+        let result = check_with(Some("_oauth2_proxy=alice"), config).await;
+        assert_eq!(result.authentication_context.user, Some("Alice".into()));
+    }
+
+    #[actix_rt::test]
+    async fn must_login() {
+        let result = check(None).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+        assert!(result.headers.get("set-cookie").is_none());
+    }
+
+    #[actix_rt::test]
+    async fn propagate_set_cookie() {
+        let result = check(Some("_oauth2_proxy=alice")).await;
+        let cookies: Vec<_> = result.headers.get_all("set-cookie").collect();
+        assert_eq!(
+            cookies,
+            vec![
+                "_oauth2_proxy=refreshed; Path=/",
+                "_oauth2_proxy_1=part; Path=/"
+            ]
+        );
+        assert!(result.headers.get("x-auth-request-email").is_none());
     }
 }
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
+
+use anyhow::Context;
+use anyhow::Result;
+
+use super::RolesLookup;
+use super::RolesLookupFactory;
+use crate::config::FileRolesConfig;
+
+/// RolesLookup that assigns roles to users from a YAML or JSON file.
+///
+/// Users not listed in the file are assigned no roles.
+#[derive(Clone)]
+pub struct FileRoles {
+    users: Arc<HashMap<String, Vec<String>>>,
+}
+
+impl FileRoles {
+    pub fn from_config(config: &FileRolesConfig) -> Result<FileRoles> {
+        let file = File::open(&config.path)
+            .with_context(|| format!("Unable to load roles file {}", config.path))?;
+        let users = serde_yaml::from_reader(file)
+            .with_context(|| format!("Unable to decode roles file {}", config.path))?;
+        Ok(FileRoles {
+            users: Arc::new(users),
+        })
+    }
+}
+
+impl RolesLookupFactory for FileRoles {
+    fn make(&self) -> Box<dyn RolesLookup> {
+        Box::new(self.clone())
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl RolesLookup for FileRoles {
+    async fn lookup(&self, user: &str) -> Result<Vec<String>> {
+        Ok(self.users.get(user).cloned().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileRoles;
+    use crate::authenticator::roles::RolesLookupFactory;
+    use crate::config::FileRolesConfig;
+
+    fn roles() -> FileRoles {
+        let config = FileRolesConfig {
+            path: "tests/fixtures/roles.yaml".into(),
+        };
+        FileRoles::from_config(&config).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn known_user() {
+        let roles = roles().make().lookup("alice").await.unwrap();
+        assert_eq!(roles, vec!["admin", "sre"]);
+    }
+
+    #[actix_rt::test]
+    async fn unknown_user() {
+        let roles = roles().make().lookup("carol").await.unwrap();
+        assert!(roles.is_empty());
+    }
+}
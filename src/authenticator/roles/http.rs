@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use anyhow::Result;
+use awc::Client;
+use serde::Deserialize;
+
+use super::RolesLookup;
+use super::RolesLookupFactory;
+use crate::authenticator::cache::TtlCache;
+use crate::config::HttpRolesConfig;
+
+/// Response expected from the roles endpoint.
+#[derive(Debug, Deserialize)]
+struct RolesResponse {
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+/// RolesLookup that requests user roles from an HTTP endpoint.
+///
+/// Users the endpoint does not know about (`404 Not Found`) are assigned no roles.
+pub struct HttpRoles {
+    cache: Arc<TtlCache<Vec<String>>>,
+    client: Client,
+    url: String,
+}
+
+impl HttpRoles {
+    /// Request the roles of the user from the endpoint.
+    async fn request(&self, user: &str) -> Result<Vec<String>> {
+        let user = percent_encoding::utf8_percent_encode(user, percent_encoding::NON_ALPHANUMERIC);
+        let url = self.url.replace("{user}", &user.to_string());
+        let mut response = self
+            .client
+            .get(&url)
+            .insert_header(("Accept", "application/json"))
+            .send()
+            .await
+            .map_err(|error| anyhow::anyhow!("unable to reach endpoint: {:?}", error))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("unexpected status code {}", response.status());
+        }
+        let response: RolesResponse = response
+            .json()
+            .await
+            .map_err(|error| anyhow::anyhow!("unable to decode response: {:?}", error))?;
+        Ok(response.roles)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl RolesLookup for HttpRoles {
+    async fn lookup(&self, user: &str) -> Result<Vec<String>> {
+        if let Some(roles) = self.cache.get(user) {
+            return Ok(roles);
+        }
+        let roles = self.request(user).await?;
+        self.cache.insert(user.to_string(), roles.clone());
+        Ok(roles)
+    }
+}
+
+/// Instantiate per-thread `HttpRoles` instances sharing the same roles cache.
+pub struct HttpRolesFactory {
+    cache: Arc<TtlCache<Vec<String>>>,
+    config: HttpRolesConfig,
+}
+
+impl HttpRolesFactory {
+    pub fn from_config(config: &HttpRolesConfig) -> HttpRolesFactory {
+        let cache = TtlCache::new(Duration::from_secs(config.cache_ttl_sec));
+        HttpRolesFactory {
+            cache: Arc::new(cache),
+            config: config.clone(),
+        }
+    }
+}
+
+impl RolesLookupFactory for HttpRolesFactory {
+    fn make(&self) -> Box<dyn RolesLookup> {
+        let client = Client::builder()
+            .disable_redirects()
+            .timeout(Duration::from_secs(self.config.timeout_sec))
+            .finish();
+        Box::new(HttpRoles {
+            cache: Arc::clone(&self.cache),
+            client,
+            url: self.config.url.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use actix_web::web;
+    use actix_web::HttpResponse;
+    use serde_json::json;
+
+    use super::HttpRolesFactory;
+    use crate::authenticator::roles::RolesLookupFactory;
+    use crate::config::HttpRolesConfig;
+
+    async fn roles(path: web::Path<String>, calls: web::Data<AtomicUsize>) -> HttpResponse {
+        calls.fetch_add(1, Ordering::SeqCst);
+        match path.as_str() {
+            "alice@example.com" => HttpResponse::Ok().json(json!({"roles": ["admin", "sre"]})),
+            "broken" => HttpResponse::InternalServerError().finish(),
+            _ => HttpResponse::NotFound().finish(),
+        }
+    }
+
+    async fn lookup(users: &[&str]) -> (Vec<Option<Vec<String>>>, usize) {
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let data = calls.clone();
+        let (address, server) = crate::authenticator::tests::mock_server(move |app| {
+            app.app_data(data.clone())
+                .route("/users/{user}/roles", web::get().to(roles));
+        });
+        let config: HttpRolesConfig =
+            serde_yaml::from_str(&format!("url: 'http://{}/users/{{user}}/roles'", address))
+                .unwrap();
+        let lookup = HttpRolesFactory::from_config(&config).make();
+        let mut results = Vec::new();
+        for user in users {
+            results.push(lookup.lookup(user).await.ok());
+        }
+        server.stop(false).await;
+        (results, calls.load(Ordering::SeqCst))
+    }
+
+    #[actix_rt::test]
+    async fn cached_roles() {
+        let (results, calls) = lookup(&["alice@example.com", "alice@example.com"]).await;
+        assert_eq!(results[0], Some(vec!["admin".into(), "sre".into()]));
+        assert_eq!(results[0], results[1]);
+        assert_eq!(calls, 1);
+    }
+
+    #[actix_rt::test]
+    async fn endpoint_error() {
+        let (results, _) = lookup(&["broken"]).await;
+        assert_eq!(results[0], None);
+    }
+
+    #[actix_rt::test]
+    async fn unknown_user() {
+        let (results, _) = lookup(&["carol"]).await;
+        assert_eq!(results[0], Some(Vec::new()));
+    }
+}
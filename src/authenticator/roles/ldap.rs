@@ -0,0 +1,219 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use anyhow::Result;
+use ldap3::Ldap;
+use ldap3::Scope;
+use ldap3::SearchEntry;
+use regex::Regex;
+
+use super::RolesLookup;
+use super::RolesLookupFactory;
+use crate::authenticator::cache::TtlCache;
+use crate::authenticator::ldap::connect;
+use crate::authenticator::ldap::search_filter;
+use crate::authenticator::ldap::user_filter;
+use crate::config::LdapRolesConfig;
+
+/// RolesLookup that maps the LDAP groups users are members of to roles.
+///
+/// Users not found in the directory are assigned no roles.
+pub struct LdapRoles {
+    cache: Arc<TtlCache<Vec<String>>>,
+    config: LdapRolesConfig,
+    pattern: Regex,
+}
+
+impl LdapRoles {
+    /// Find the DNs of the groups the user is a member of.
+    async fn groups(&self, ldap: &mut Ldap, user: &str) -> Result<Vec<String>> {
+        let timeout = Duration::from_secs(self.config.timeout_sec);
+        let bind_dn = self.config.bind_dn.as_deref().unwrap_or("");
+        let bind_password = self.config.bind_password.as_deref().unwrap_or("");
+        ldap.with_timeout(timeout)
+            .simple_bind(bind_dn, bind_password)
+            .await?
+            .success()?;
+
+        // Search for the user entry.
+        let filter = user_filter(&self.config.filter, user);
+        let attributes = vec![self.config.member_attribute.as_str()];
+        let (entries, _) = ldap
+            .with_timeout(timeout)
+            .search(&self.config.base_dn, Scope::Subtree, &filter, attributes)
+            .await?
+            .success()?;
+        let mut entry = match entries.len() {
+            0 => {
+                log::debug!("LDAP user '{}' not found", user);
+                return Ok(Vec::new());
+            }
+            1 => SearchEntry::construct(entries.into_iter().next().unwrap()),
+            count => anyhow::bail!("found {} LDAP entries for user '{}'", count, user),
+        };
+
+        // Search for groups listing the user as a member, if configured.
+        let group_base_dn = match &self.config.group_base_dn {
+            None => {
+                let groups = entry.attrs.remove(&self.config.member_attribute);
+                return Ok(groups.unwrap_or_default());
+            }
+            Some(group_base_dn) => group_base_dn,
+        };
+        let filter = search_filter(
+            &self.config.group_filter,
+            &[("{dn}", &entry.dn), ("{user}", user)],
+        );
+        let (entries, _) = ldap
+            .with_timeout(timeout)
+            .search(group_base_dn, Scope::Subtree, &filter, vec!["1.1"])
+            .await?
+            .success()?;
+        let groups = entries
+            .into_iter()
+            .map(|entry| SearchEntry::construct(entry).dn)
+            .collect();
+        Ok(groups)
+    }
+
+    /// Map group DNs to roles, ignoring DNs that don't match the role pattern.
+    fn roles(&self, groups: Vec<String>) -> Vec<String> {
+        let mut roles: Vec<String> = Vec::new();
+        for group in groups {
+            let role = match self.pattern.captures(&group) {
+                None => continue,
+                Some(captures) => captures.get(1).or_else(|| captures.get(0)),
+            };
+            if let Some(role) = role {
+                let role = role.as_str().to_string();
+                if !roles.contains(&role) {
+                    roles.push(role);
+                }
+            }
+        }
+        roles
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl RolesLookup for LdapRoles {
+    async fn lookup(&self, user: &str) -> Result<Vec<String>> {
+        if let Some(roles) = self.cache.get(user) {
+            return Ok(roles);
+        }
+        let timeout = Duration::from_secs(self.config.timeout_sec);
+        let mut ldap = connect(&self.config.url, self.config.starttls, timeout).await?;
+        let groups = self.groups(&mut ldap, user).await;
+        let _ = ldap.unbind().await;
+        let roles = self.roles(groups?);
+        self.cache.insert(user.to_string(), roles.clone());
+        Ok(roles)
+    }
+}
+
+/// Instantiate per-thread `LdapRoles` instances sharing the same roles cache.
+pub struct LdapRolesFactory {
+    cache: Arc<TtlCache<Vec<String>>>,
+    config: LdapRolesConfig,
+    pattern: Regex,
+}
+
+impl LdapRolesFactory {
+    pub fn from_config(config: &LdapRolesConfig) -> Result<LdapRolesFactory> {
+        if !config.filter.contains("{user}") {
+            anyhow::bail!("LDAP filter must include the `{{user}}` placeholder");
+        }
+        let pattern = Regex::new(&config.role_pattern)
+            .with_context(|| format!("Invalid LDAP role pattern `{}`", config.role_pattern))?;
+        let cache = TtlCache::new(Duration::from_secs(config.cache_ttl_sec));
+        Ok(LdapRolesFactory {
+            cache: Arc::new(cache),
+            config: config.clone(),
+            pattern,
+        })
+    }
+}
+
+impl RolesLookupFactory for LdapRolesFactory {
+    fn make(&self) -> Box<dyn RolesLookup> {
+        Box::new(LdapRoles {
+            cache: Arc::clone(&self.cache),
+            config: self.config.clone(),
+            pattern: self.pattern.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::atomic::Ordering;
+
+    use super::LdapRolesFactory;
+    use crate::authenticator::ldap::tests::mock_directory;
+    use crate::authenticator::roles::RolesLookupFactory;
+    use crate::config::LdapRolesConfig;
+
+    fn factory(address: SocketAddr, extra: &str) -> LdapRolesFactory {
+        let config: LdapRolesConfig = serde_yaml::from_str(&format!(
+            r#"
+            base-dn: 'dc=example,dc=com'
+            bind-dn: 'cn=admin,dc=example,dc=com'
+            bind-password: admin-password
+            url: 'ldap://{}'
+            {}
+            "#,
+            address, extra
+        ))
+        .unwrap();
+        LdapRolesFactory::from_config(&config).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn group_search() {
+        let (address, _) = mock_directory();
+        let factory = factory(address, "group-base-dn: 'ou=groups,dc=example,dc=com'");
+        let roles = factory.make().lookup("bob").await.unwrap();
+        assert_eq!(roles, vec!["dev"]);
+    }
+
+    #[test]
+    fn invalid_role_pattern() {
+        let config: LdapRolesConfig = serde_yaml::from_str(
+            r#"
+            base-dn: 'dc=example,dc=com'
+            role-pattern: 'cn=('
+            url: 'ldap://localhost'
+            "#,
+        )
+        .unwrap();
+        assert!(LdapRolesFactory::from_config(&config).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn member_of() {
+        let (address, binds) = mock_directory();
+        let factory = factory(address, "");
+        let roles = factory.make().lookup("alice").await.unwrap();
+        assert_eq!(roles, vec!["dev", "SRE"]);
+        // Roles are cached per user.
+        factory.make().lookup("alice").await.unwrap();
+        assert_eq!(binds.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_rt::test]
+    async fn role_pattern() {
+        let (address, _) = mock_directory();
+        let factory = factory(address, "role-pattern: '^cn=SRE,'");
+        let roles = factory.make().lookup("alice").await.unwrap();
+        assert_eq!(roles, vec!["cn=SRE,"]);
+    }
+
+    #[actix_rt::test]
+    async fn unknown_user() {
+        let (address, _) = mock_directory();
+        let roles = factory(address, "").make().lookup("mallory").await.unwrap();
+        assert!(roles.is_empty());
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::config::RolesBackend;
+
+mod file;
+mod http;
+mod ldap;
+mod noop;
+mod static_roles;
+
+pub use self::noop::NoopRoles;
+
+/// Interface to user roles lookup implementations.
+#[async_trait::async_trait(?Send)]
+pub trait RolesLookup {
+    /// Lookup the roles assigned to the user.
+    async fn lookup(&self, user: &str) -> Result<Vec<String>>;
+}
+
+/// Thread-safe logic to create thread-scoped `RolesLookup` instances.
+pub trait RolesLookupFactory: Send + Sync {
+    /// Return a new `RolesLookup` instance.
+    fn make(&self) -> Box<dyn RolesLookup>;
+}
+
+/// Create a `RolesLookupFactory` for the configured backend.
+pub fn factory(backend: &RolesBackend) -> Result<Arc<dyn RolesLookupFactory>> {
+    let factory: Arc<dyn RolesLookupFactory> = match backend {
+        RolesBackend::File(file) => Arc::new(self::file::FileRoles::from_config(file)?),
+        RolesBackend::Http(http) => Arc::new(self::http::HttpRolesFactory::from_config(http)),
+        RolesBackend::Ldap(ldap) => Arc::new(self::ldap::LdapRolesFactory::from_config(ldap)?),
+        RolesBackend::Noop => Arc::new(NoopRoles {}),
+        RolesBackend::Static(roles) => {
+            Arc::new(self::static_roles::StaticRoles::from_config(roles))
+        }
+    };
+    Ok(factory)
+}
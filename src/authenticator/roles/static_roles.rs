@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use super::RolesLookup;
+use super::RolesLookupFactory;
+use crate::config::StaticRolesConfig;
+
+/// RolesLookup that assigns the same roles to all users.
+#[derive(Clone)]
+pub struct StaticRoles {
+    roles: Arc<Vec<String>>,
+}
+
+impl StaticRoles {
+    pub fn from_config(config: &StaticRolesConfig) -> StaticRoles {
+        StaticRoles {
+            roles: Arc::new(config.roles.clone()),
+        }
+    }
+}
+
+impl RolesLookupFactory for StaticRoles {
+    fn make(&self) -> Box<dyn RolesLookup> {
+        Box::new(self.clone())
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl RolesLookup for StaticRoles {
+    async fn lookup(&self, _: &str) -> Result<Vec<String>> {
+        Ok(self.roles.as_ref().clone())
+    }
+}
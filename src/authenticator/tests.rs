@@ -1,15 +1,20 @@
 use actix_web::http::header::HeaderMap;
 use actix_web::http::header::HeaderName;
 use actix_web::http::header::HeaderValue;
+use actix_web::test::TestRequest;
 use actix_web::HttpRequest;
 use anyhow::Result;
 
+use crate::authenticator::roles::RolesLookup;
 use crate::authenticator::AuthenticationProxy;
+use crate::config::RequestExtraction;
 use crate::models::AuditReason;
 use crate::models::AuthenticationContext;
 use crate::models::AuthenticationResult;
 use crate::models::AuthenticationStatus;
 use crate::models::RequestContext;
+use crate::models::RuleDomains;
+use crate::models::RuleMatches;
 
 /// Mock authenticator for tests.
 pub struct Authenticator {
@@ -21,31 +26,38 @@ pub struct Authenticator {
 impl Authenticator {
     /// Return an authenticator for the authenticated Alice user.
     pub fn alice() -> Authenticator {
-        let mut auth = Authenticator::default();
-        auth.context = AuthenticationContext {
-            authenticated: true,
-            user: Some("alice".to_string()),
-            session: None,
-        };
-        auth
+        Authenticator {
+            context: AuthenticationContext {
+                authenticated: true,
+                attributes: Default::default(),
+                groups: Default::default(),
+                roles: Default::default(),
+                user: Some("alice".to_string()),
+                session: None,
+            },
+            ..Default::default()
+        }
     }
 
     pub fn denied() -> Authenticator {
-        let mut auth = Authenticator::default();
-        auth.check_result = AuthenticationStatus::Denied;
-        auth
+        Authenticator {
+            check_result: AuthenticationStatus::Denied,
+            ..Default::default()
+        }
     }
 
     pub fn failing() -> Authenticator {
-        let mut auth = Authenticator::default();
-        auth.fail_check = true;
-        auth
+        Authenticator {
+            fail_check: true,
+            ..Default::default()
+        }
     }
 
     pub fn must_login() -> Authenticator {
-        let mut auth = Authenticator::default();
-        auth.check_result = AuthenticationStatus::MustLogin;
-        auth
+        Authenticator {
+            check_result: AuthenticationStatus::MustLogin,
+            ..Default::default()
+        }
     }
 }
 
@@ -77,6 +89,7 @@ impl AuthenticationProxy for Authenticator {
         Ok(AuthenticationResult {
             audit_reason,
             authentication_context: self.context.clone(),
+            authenticator: None,
             headers,
             status,
         })
@@ -92,3 +105,107 @@ impl Default for Authenticator {
         }
     }
 }
+
+/// Mock roles lookup for tests.
+pub struct Roles {
+    fail_lookup: bool,
+}
+
+impl Roles {
+    pub fn failing() -> Roles {
+        Roles { fail_lookup: true }
+    }
+
+    pub fn sre() -> Roles {
+        Roles { fail_lookup: false }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl RolesLookup for Roles {
+    async fn lookup(&self, _: &str) -> Result<Vec<String>> {
+        if self.fail_lookup {
+            anyhow::bail!("Test roles lookup returning error");
+        }
+        Ok(vec!["sre".to_string()])
+    }
+}
+
+/// Start a local HTTP server to mock remote services and return its address.
+///
+/// The server is bound to a random port and must be stopped by the caller.
+pub fn mock_server<F>(configure: F) -> (std::net::SocketAddr, actix_web::dev::ServerHandle)
+where
+    F: Fn(&mut actix_web::web::ServiceConfig) + Clone + Send + 'static,
+{
+    let server =
+        actix_web::HttpServer::new(move || actix_web::App::new().configure(configure.clone()))
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .unwrap();
+    let address = server.addrs()[0];
+    let server = server.run();
+    let handle = server.handle();
+    actix_rt::spawn(server);
+    (address, handle)
+}
+
+fn select_domain(domain: &str) -> RuleMatches {
+    RuleMatches {
+        domain: RuleDomains::from_static(&[domain]),
+        header_equal: Default::default(),
+        uri: Default::default(),
+        ..Default::default()
+    }
+}
+
+async fn check_host(auth: &super::Authenticator, host: &str) -> AuthenticationResult {
+    let request = TestRequest::get()
+        .append_header(("Host", host))
+        .append_header(("X-Forwarded-Proto", "https"))
+        .append_header(("X-Original-URI", "/"))
+        .to_http_request();
+    let context = RequestContext::from_request(&request, &RequestExtraction::default()).unwrap();
+    auth.check(&context, &request).await.unwrap()
+}
+
+#[actix_rt::test]
+async fn select_authenticator_by_domain() {
+    let auth = super::Authenticator::from(Authenticator::must_login())
+        .select(select_domain("api.example.com"), Authenticator::alice());
+    let result = check_host(&auth, "api.example.com").await;
+    assert_eq!(result.status, AuthenticationStatus::Allowed);
+    assert_eq!(result.authentication_context.user, Some("alice".into()));
+}
+
+#[actix_rt::test]
+async fn select_default_authenticator() {
+    let auth = super::Authenticator::from(Authenticator::must_login())
+        .select(select_domain("api.example.com"), Authenticator::alice());
+    let result = check_host(&auth, "grafana.example.com").await;
+    assert_eq!(result.status, AuthenticationStatus::MustLogin);
+}
+
+#[actix_rt::test]
+async fn lookup_roles() {
+    let auth = super::Authenticator::from(Authenticator::alice()).with_roles(Roles::sre());
+    let result = check_host(&auth, "grafana.example.com").await;
+    assert_eq!(result.status, AuthenticationStatus::Allowed);
+    assert_eq!(result.authentication_context.roles, vec!["sre"]);
+}
+
+#[actix_rt::test]
+async fn lookup_roles_anonymous() {
+    let auth = super::Authenticator::from(Authenticator::default()).with_roles(Roles::failing());
+    let result = check_host(&auth, "grafana.example.com").await;
+    assert_eq!(result.status, AuthenticationStatus::Allowed);
+    assert!(result.authentication_context.roles.is_empty());
+}
+
+#[actix_rt::test]
+async fn lookup_roles_error() {
+    let auth = super::Authenticator::from(Authenticator::alice()).with_roles(Roles::failing());
+    let result = check_host(&auth, "grafana.example.com").await;
+    assert_eq!(result.status, AuthenticationStatus::Denied);
+    assert_eq!(result.audit_reason, AuditReason::RolesLookupFailed);
+}
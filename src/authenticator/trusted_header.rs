@@ -0,0 +1,248 @@
+use actix_web::HttpRequest;
+use anyhow::Result;
+
+use crate::authenticator::http::extract_header;
+use crate::authenticator::http::extract_list;
+use crate::authenticator::AuthenticationProxy;
+use crate::authenticator::AuthenticationProxyFactory;
+use crate::config::TrustedHeaderConfig;
+use crate::models::AuthenticationResult;
+use crate::models::AuthenticationStatus;
+use crate::models::RequestContext;
+
+/// Authenticate users with identity headers set by a trusted upstream identity layer.
+#[derive(Clone)]
+pub struct TrustedHeader {
+    config: TrustedHeaderConfig,
+    secret_hash: Option<String>,
+}
+
+impl TrustedHeader {
+    /// Check if the request comes from a trusted source or carries the shared secret.
+    ///
+    /// The source is the client IP address resolved by the request context, never addresses
+    /// from headers the client controls or the HTTPS proxy connecting to AuthGateway.
+    fn trusted(&self, context: &RequestContext, request: &HttpRequest) -> bool {
+        if !self.config.trusted_cidrs.is_empty() {
+            if let Some(source) = context.client_ip {
+                if self
+                    .config
+                    .trusted_cidrs
+                    .iter()
+                    .any(|cidr| cidr.contains(&source))
+                {
+                    return true;
+                }
+            }
+        }
+        match (&self.config.secret_header, &self.secret_hash) {
+            (Some(header), Some(expected)) => extract_header(request.headers(), header)
+                .map(|secret| &crate::authenticator::hash_token(&secret) == expected)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl AuthenticationProxy for TrustedHeader {
+    async fn check(
+        &self,
+        context: &RequestContext,
+        request: &HttpRequest,
+    ) -> Result<AuthenticationResult> {
+        let headers = request.headers();
+        let user = extract_header(headers, &self.config.user_header);
+        let user = match (user, self.trusted(context, request)) {
+            (None, _) => {
+                return Ok(AuthenticationResult::from_status(
+                    AuthenticationStatus::MustLogin,
+                ))
+            }
+            (Some(user), true) => user,
+            (Some(user), false) => {
+                log::warn!(
+                    "Rejected identity header for user '{}' from untrusted upstream {:?}",
+                    user,
+                    context.client_ip
+                );
+                return Ok(AuthenticationResult::denied());
+            }
+        };
+
+        let mut result = AuthenticationResult::from_status(AuthenticationStatus::Allowed);
+        let context = &mut result.authentication_context;
+        context.authenticated = true;
+        context.user = Some(user);
+        if let Some(header) = &self.config.email_header {
+            if let Some(email) = extract_header(headers, header) {
+                context.attributes.insert("email".into(), email);
+            }
+        }
+        if let Some(header) = &self.config.groups_header {
+            context.groups = extract_list(headers, header, &self.config.groups_separator);
+        }
+        Ok(result)
+    }
+}
+
+/// Instantiate per-thread `TrustedHeader` instances.
+pub struct TrustedHeaderFactory {
+    proxy: TrustedHeader,
+}
+
+impl TrustedHeaderFactory {
+    pub fn from_config(config: &TrustedHeaderConfig) -> Result<TrustedHeaderFactory> {
+        if config.secret.is_some() != config.secret_header.is_some() {
+            anyhow::bail!("trusted-header `secret` and `secret-header` must be set together");
+        }
+        if config.trusted_cidrs.is_empty() && config.secret.is_none() {
+            anyhow::bail!("trusted-header needs `trusted-cidrs` or a `secret` to trust upstreams");
+        }
+        let secret_hash = config
+            .secret
+            .as_ref()
+            .map(|secret| crate::authenticator::hash_token(secret));
+        let proxy = TrustedHeader {
+            config: config.clone(),
+            secret_hash,
+        };
+        Ok(TrustedHeaderFactory { proxy })
+    }
+}
+
+impl AuthenticationProxyFactory for TrustedHeaderFactory {
+    fn make(&self) -> Box<dyn AuthenticationProxy> {
+        Box::new(self.proxy.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::TrustedHeaderFactory;
+    use crate::authenticator::AuthenticationProxyFactory;
+    use crate::config::RequestExtraction;
+    use crate::config::TrustedHeaderConfig;
+    use crate::models::AuthenticationResult;
+    use crate::models::AuthenticationStatus;
+    use crate::models::RequestContext;
+
+    fn factory() -> TrustedHeaderFactory {
+        let config: TrustedHeaderConfig = serde_yaml::from_str(
+            r#"
+            email-header: x-sso-email
+            groups-header: x-sso-groups
+            secret: sso-secret
+            secret-header: x-sso-secret
+            trusted-cidrs: ['10.0.0.0/8']
+            user-header: x-sso-user
+            "#,
+        )
+        .unwrap();
+        TrustedHeaderFactory::from_config(&config).unwrap()
+    }
+
+    async fn check(peer: &str, headers: &[(&str, &str)]) -> AuthenticationResult {
+        check_with(&RequestExtraction::default(), peer, headers).await
+    }
+
+    async fn check_with(
+        extraction: &RequestExtraction,
+        peer: &str,
+        headers: &[(&str, &str)],
+    ) -> AuthenticationResult {
+        let mut request = TestRequest::get()
+            .peer_addr(peer.parse().unwrap())
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"));
+        for header in headers {
+            request = request.append_header(*header);
+        }
+        let request = request.to_http_request();
+        let context = RequestContext::from_request(&request, extraction).unwrap();
+        factory().make().check(&context, &request).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn missing_user_header() {
+        let result = check("10.1.2.3:1234", &[]).await;
+        assert_eq!(result.status, AuthenticationStatus::MustLogin);
+    }
+
+    #[test]
+    fn needs_trust_condition() {
+        let config: TrustedHeaderConfig = serde_yaml::from_str("user-header: x-sso-user").unwrap();
+        assert!(TrustedHeaderFactory::from_config(&config).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn trusted_cidr() {
+        let headers = [
+            ("X-Real-IP", "10.1.2.3"),
+            ("X-SSO-User", "alice"),
+            ("X-SSO-Email", "alice@example.com"),
+            ("X-SSO-Groups", "admins,sre"),
+        ];
+        let result = check("192.168.1.1:1234", &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+        let context = &result.authentication_context;
+        assert!(context.authenticated);
+        assert_eq!(context.user, Some("alice".into()));
+        assert_eq!(context.attributes["email"], "alice@example.com");
+        assert_eq!(context.groups, vec!["admins", "sre"]);
+    }
+
+    #[actix_rt::test]
+    async fn trusted_forwarded_for() {
+        let extraction = RequestExtraction {
+            trusted_proxies: Some(1),
+            ..Default::default()
+        };
+        let headers = [
+            ("X-Forwarded-For", "192.0.2.1, 10.1.2.3"),
+            ("X-SSO-User", "alice"),
+        ];
+        let result = check_with(&extraction, "192.168.1.1:1234", &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+    }
+
+    #[actix_rt::test]
+    async fn untrusted_proxy_peer() {
+        // Behind the HTTPS proxy the peer is always the proxy, which must not be trusted.
+        let headers = [("X-SSO-User", "alice")];
+        let result = check("10.1.2.3:1234", &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn untrusted_spoofed_forwarded_for() {
+        let extraction = RequestExtraction {
+            trusted_proxies: Some(1),
+            ..Default::default()
+        };
+        let headers = [
+            ("X-Forwarded-For", "10.1.2.3, 192.0.2.1"),
+            ("X-SSO-User", "alice"),
+        ];
+        let result = check_with(&extraction, "10.1.2.3:1234", &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+    }
+
+    #[actix_rt::test]
+    async fn trusted_secret() {
+        let headers = [("X-SSO-User", "alice"), ("X-SSO-Secret", "sso-secret")];
+        let result = check("192.168.1.1:1234", &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Allowed);
+    }
+
+    #[actix_rt::test]
+    async fn untrusted_source() {
+        let headers = [("X-SSO-User", "alice"), ("X-SSO-Secret", "wrong")];
+        let result = check("192.168.1.1:1234", &headers).await;
+        assert_eq!(result.status, AuthenticationStatus::Denied);
+        assert_eq!(result.authentication_context.user, None);
+    }
+}
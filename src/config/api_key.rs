@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// API key backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiKeyConfig {
+    /// Request header to look for API keys in.
+    ///
+    /// API keys are also accepted as bearer tokens in the `Authorization` header.
+    #[serde(default = "ApiKeyConfig::default_header")]
+    pub header: String,
+
+    /// List of API keys accepted by the authenticator.
+    #[serde(default)]
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+impl ApiKeyConfig {
+    fn default_header() -> String {
+        "x-api-key".into()
+    }
+}
+
+/// An API key accepted by the authenticator and the identity it maps to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiKeyEntry {
+    /// Extra attributes about the owner of the key.
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+
+    /// Hex encoded SHA3-512 digest of the API key.
+    ///
+    /// Keys are never stored in plain text, a digest can be generated with
+    /// `echo -n "$API_KEY" | openssl dgst -sha3-512`.
+    pub hash: String,
+
+    /// ID of the user authenticated by this key.
+    pub user: String,
+}
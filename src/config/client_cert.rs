@@ -0,0 +1,88 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Client certificate (mTLS) identity backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClientCertConfig {
+    /// Authenticate requests with only the subject DN header, without the certificate.
+    ///
+    /// The DN can't be verified so it must be set by the proxy after verifying the certificate.
+    #[serde(default, rename = "allow-subject-dn-only")]
+    pub allow_subject_dn_only: bool,
+
+    /// Path to a PEM bundle of CA certificates client certificates must be issued by.
+    ///
+    /// When set, requests must include the client certificate in the `cert-header`.
+    #[serde(default, rename = "ca-file")]
+    pub ca_file: Option<String>,
+
+    /// Request header with the URL-encoded PEM client certificate.
+    #[serde(
+        default = "ClientCertConfig::default_cert_header",
+        rename = "cert-header"
+    )]
+    pub cert_header: Option<String>,
+
+    /// Path to a PEM or DER certificate revocation list to reject revoked certificates.
+    ///
+    /// When set, requests must include the client certificate in the `cert-header`.
+    #[serde(default, rename = "crl-file")]
+    pub crl_file: Option<String>,
+
+    /// Request header with the client certificate subject DN.
+    ///
+    /// Only used when the client certificate is not available and `allow-subject-dn-only` is set.
+    #[serde(
+        default = "ClientCertConfig::default_subject_dn_header",
+        rename = "subject-dn-header"
+    )]
+    pub subject_dn_header: Option<String>,
+
+    /// Certificate field to use as the user ID.
+    #[serde(default, rename = "user-source")]
+    pub user_source: ClientCertUserSource,
+
+    /// Request header with the result of the client certificate verification by the proxy.
+    ///
+    /// Requests are only authenticated if this header is `SUCCESS`.
+    #[serde(
+        default = "ClientCertConfig::default_verify_header",
+        rename = "verify-header"
+    )]
+    pub verify_header: Option<String>,
+}
+
+impl ClientCertConfig {
+    fn default_cert_header() -> Option<String> {
+        Some("x-ssl-client-cert".into())
+    }
+
+    fn default_subject_dn_header() -> Option<String> {
+        Some("x-ssl-client-s-dn".into())
+    }
+
+    fn default_verify_header() -> Option<String> {
+        Some("x-ssl-client-verify".into())
+    }
+}
+
+/// Certificate fields the user ID can be derived from.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ClientCertUserSource {
+    /// The first DNS name in the Subject Alternative Name extension.
+    #[serde(rename = "san-dns")]
+    SanDns,
+
+    /// The first email address in the Subject Alternative Name extension.
+    #[serde(rename = "san-email")]
+    SanEmail,
+
+    /// The first URI in the Subject Alternative Name extension.
+    #[serde(rename = "san-uri")]
+    SanUri,
+
+    /// The Common Name (CN) of the certificate subject.
+    #[default]
+    #[serde(rename = "subject-cn")]
+    SubjectCn,
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::models::AuthenticationStatus;
+
+/// Generic HTTP forward authentication backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ForwardAuthConfig {
+    /// Response header to fetch the user email from.
+    #[serde(
+        default = "ForwardAuthConfig::default_email_header",
+        rename = "email-header"
+    )]
+    pub email_header: Option<String>,
+
+    /// Response header to fetch the list of user groups from.
+    #[serde(
+        default = "ForwardAuthConfig::default_groups_header",
+        rename = "groups-header"
+    )]
+    pub groups_header: Option<String>,
+
+    /// Separator between groups in the groups response header.
+    #[serde(
+        default = "ForwardAuthConfig::default_groups_separator",
+        rename = "groups-separator"
+    )]
+    pub groups_separator: String,
+
+    /// Response headers to copy from the authentication service into AuthGateway responses.
+    ///
+    /// Useful to propagate session cookies refreshed by the authentication service.
+    #[serde(default, rename = "response-headers")]
+    pub response_headers: Vec<String>,
+
+    /// Response header to derive the session ID from.
+    ///
+    /// The value of the header is hashed so it is safe to use tokens as session IDs.
+    #[serde(default, rename = "session-header")]
+    pub session_header: Option<String>,
+
+    /// Map authentication service response status codes to authentication results.
+    ///
+    /// Requests are denied if the service responds with any other status code.
+    #[serde(default = "ForwardAuthConfig::default_statuses")]
+    pub statuses: HashMap<u16, AuthenticationStatus>,
+
+    /// Timeout (in seconds) to wait for the authentication service to respond.
+    #[serde(
+        default = "ForwardAuthConfig::default_timeout_sec",
+        rename = "timeout-sec"
+    )]
+    pub timeout_sec: u64,
+
+    /// URL of the authentication service endpoint to forward check requests to.
+    pub url: String,
+
+    /// Response header to fetch the user ID from.
+    #[serde(
+        default = "ForwardAuthConfig::default_user_header",
+        rename = "user-header"
+    )]
+    pub user_header: Option<String>,
+}
+
+impl ForwardAuthConfig {
+    fn default_email_header() -> Option<String> {
+        Some("remote-email".into())
+    }
+
+    fn default_groups_header() -> Option<String> {
+        Some("remote-groups".into())
+    }
+
+    fn default_groups_separator() -> String {
+        ",".into()
+    }
+
+    fn default_statuses() -> HashMap<u16, AuthenticationStatus> {
+        let mut statuses = HashMap::new();
+        statuses.insert(200, AuthenticationStatus::Allowed);
+        statuses.insert(202, AuthenticationStatus::Allowed);
+        statuses.insert(204, AuthenticationStatus::Allowed);
+        statuses.insert(401, AuthenticationStatus::MustLogin);
+        statuses.insert(403, AuthenticationStatus::Denied);
+        statuses
+    }
+
+    fn default_timeout_sec() -> u64 {
+        5
+    }
+
+    fn default_user_header() -> Option<String> {
+        Some("remote-user".into())
+    }
+}
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// HTTP Basic authentication with an htpasswd file backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HtpasswdConfig {
+    /// Path to the Apache-style htpasswd file to load users from.
+    ///
+    /// Supported password hashes are bcrypt, SHA1 (`{SHA}`) and argon2.
+    pub file: String,
+
+    /// Realm to report to clients in the `WWW-Authenticate` header.
+    #[serde(default = "HtpasswdConfig::default_realm")]
+    pub realm: String,
+}
+
+impl HtpasswdConfig {
+    fn default_realm() -> String {
+        "AuthGateway".into()
+    }
+}
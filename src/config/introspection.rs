@@ -0,0 +1,44 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// OAuth2 token introspection (RFC 7662) backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IntrospectionConfig {
+    /// Time (in seconds) to cache introspection results for active tokens.
+    ///
+    /// Revoked tokens may be accepted until the cached result expires.
+    /// Set to 0 to introspect tokens on every request.
+    #[serde(
+        default = "IntrospectionConfig::default_cache_ttl_sec",
+        rename = "cache-ttl-sec"
+    )]
+    pub cache_ttl_sec: u64,
+
+    /// Client ID to authenticate with the introspection endpoint.
+    #[serde(rename = "client-id")]
+    pub client_id: String,
+
+    /// Client secret to authenticate with the introspection endpoint.
+    #[serde(rename = "client-secret")]
+    pub client_secret: String,
+
+    /// Timeout (in seconds) to wait for the introspection endpoint to respond.
+    #[serde(
+        default = "IntrospectionConfig::default_timeout_sec",
+        rename = "timeout-sec"
+    )]
+    pub timeout_sec: u64,
+
+    /// URL of the token introspection endpoint.
+    pub url: String,
+}
+
+impl IntrospectionConfig {
+    fn default_cache_ttl_sec() -> u64 {
+        60
+    }
+
+    fn default_timeout_sec() -> u64 {
+        5
+    }
+}
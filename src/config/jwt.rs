@@ -0,0 +1,102 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// JWT bearer token backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JwtConfig {
+    /// Signing algorithms tokens are allowed to use.
+    #[serde(default = "JwtConfig::default_algorithms")]
+    pub algorithms: Vec<JwtAlgorithm>,
+
+    /// Accept only tokens issued for any of these audiences (`aud` claim).
+    ///
+    /// At least one audience is required so tokens for other services are rejected.
+    #[serde(default)]
+    pub audience: Vec<String>,
+
+    /// Accept only tokens issued by any of these issuers (`iss` claim).
+    ///
+    /// At least one issuer is required.
+    #[serde(default)]
+    pub issuer: Vec<String>,
+
+    /// Path to a local file to load the JSON Web Key Set from.
+    #[serde(default, rename = "jwks-file")]
+    pub jwks_file: Option<String>,
+
+    /// URL to fetch the JSON Web Key Set from.
+    #[serde(default, rename = "jwks-url")]
+    pub jwks_url: Option<String>,
+
+    /// Interval (in seconds) between JSON Web Key Set refreshes.
+    #[serde(
+        default = "JwtConfig::default_jwks_refresh_sec",
+        rename = "jwks-refresh-sec"
+    )]
+    pub jwks_refresh_sec: u64,
+
+    /// Clock skew (in seconds) allowed when checking `exp` and `nbf` claims.
+    #[serde(default = "JwtConfig::default_leeway_sec", rename = "leeway-sec")]
+    pub leeway_sec: u64,
+
+    /// Timeout (in seconds) to wait for the JSON Web Key Set URL to respond.
+    #[serde(default = "JwtConfig::default_timeout_sec", rename = "timeout-sec")]
+    pub timeout_sec: u64,
+
+    /// Token claim to use as the user ID.
+    #[serde(default = "JwtConfig::default_user_claim", rename = "user-claim")]
+    pub user_claim: String,
+}
+
+impl JwtConfig {
+    fn default_algorithms() -> Vec<JwtAlgorithm> {
+        vec![
+            JwtAlgorithm::RS256,
+            JwtAlgorithm::ES256,
+            JwtAlgorithm::EdDSA,
+        ]
+    }
+
+    fn default_jwks_refresh_sec() -> u64 {
+        300
+    }
+
+    fn default_leeway_sec() -> u64 {
+        60
+    }
+
+    fn default_timeout_sec() -> u64 {
+        5
+    }
+
+    fn default_user_claim() -> String {
+        "sub".into()
+    }
+}
+
+/// Supported JWT signing algorithms.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum JwtAlgorithm {
+    /// ECDSA using P-256 and SHA-256.
+    ES256,
+
+    /// EdDSA using Ed25519.
+    EdDSA,
+
+    /// HMAC using SHA-256 with a shared secret.
+    HS256,
+
+    /// RSASSA-PKCS1-v1_5 using SHA-256.
+    RS256,
+}
+
+impl From<JwtAlgorithm> for jsonwebtoken::Algorithm {
+    fn from(algorithm: JwtAlgorithm) -> jsonwebtoken::Algorithm {
+        match algorithm {
+            JwtAlgorithm::ES256 => jsonwebtoken::Algorithm::ES256,
+            JwtAlgorithm::EdDSA => jsonwebtoken::Algorithm::EdDSA,
+            JwtAlgorithm::HS256 => jsonwebtoken::Algorithm::HS256,
+            JwtAlgorithm::RS256 => jsonwebtoken::Algorithm::RS256,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// LDAP search-and-bind authentication with HTTP Basic credentials backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LdapConfig {
+    /// Base DN to search users under.
+    #[serde(rename = "base-dn")]
+    pub base_dn: String,
+
+    /// DN to bind as to search for users.
+    ///
+    /// The search is performed anonymously if no DN is set.
+    #[serde(default, rename = "bind-dn")]
+    pub bind_dn: Option<String>,
+
+    /// Password of the `bind-dn` user.
+    #[serde(default, rename = "bind-password")]
+    pub bind_password: Option<String>,
+
+    /// Time (in seconds) to cache successful binds for.
+    ///
+    /// Password changes and disabled users may take this long to be noticed.
+    /// Set to 0 to check credentials with the directory on every request.
+    #[serde(
+        default = "LdapConfig::default_cache_ttl_sec",
+        rename = "cache-ttl-sec"
+    )]
+    pub cache_ttl_sec: u64,
+
+    /// Filter to search for the user with, `{user}` is replaced by the (escaped) username.
+    #[serde(default = "LdapConfig::default_filter")]
+    pub filter: String,
+
+    /// Realm to report to clients in the `WWW-Authenticate` header.
+    #[serde(default = "LdapConfig::default_realm")]
+    pub realm: String,
+
+    /// Upgrade `ldap://` connections to TLS with the StartTLS operation.
+    #[serde(default)]
+    pub starttls: bool,
+
+    /// Timeout (in seconds) to wait for the directory to respond to each operation.
+    #[serde(default = "LdapConfig::default_timeout_sec", rename = "timeout-sec")]
+    pub timeout_sec: u64,
+
+    /// URL of the directory to connect to (`ldap://` or `ldaps://`).
+    pub url: String,
+
+    /// Attribute of the user entry to use as the user ID.
+    #[serde(
+        default = "LdapConfig::default_user_attribute",
+        rename = "user-attribute"
+    )]
+    pub user_attribute: String,
+}
+
+impl LdapConfig {
+    fn default_cache_ttl_sec() -> u64 {
+        30
+    }
+
+    fn default_filter() -> String {
+        "(uid={user})".into()
+    }
+
+    fn default_realm() -> String {
+        "AuthGateway".into()
+    }
+
+    fn default_timeout_sec() -> u64 {
+        5
+    }
+
+    fn default_user_attribute() -> String {
+        "uid".into()
+    }
+}
@@ -1,20 +1,53 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::path::Path;
 
 use anyhow::Context;
 use anyhow::Result;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::value::SeqAccessDeserializer;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
 
+use crate::models::RuleMatches;
+
+mod api_key;
+mod client_cert;
+mod forward_auth;
+mod htpasswd;
+mod introspection;
+mod jwt;
+mod ldap;
 mod mongodb;
 mod oauth2_proxy;
-
+mod roles;
+mod trusted_header;
+
+pub use self::api_key::ApiKeyConfig;
+pub use self::api_key::ApiKeyEntry;
+pub use self::client_cert::ClientCertConfig;
+pub use self::client_cert::ClientCertUserSource;
+pub use self::forward_auth::ForwardAuthConfig;
+pub use self::htpasswd::HtpasswdConfig;
+pub use self::introspection::IntrospectionConfig;
+pub use self::jwt::JwtConfig;
+pub use self::ldap::LdapConfig;
 pub use self::mongodb::MongoDBAuditConfig;
 pub use self::oauth2_proxy::OAuth2ProxyConfig;
 pub use self::oauth2_proxy::OAuth2ProxyUserIdSourceHeader;
+pub use self::roles::FileRolesConfig;
+pub use self::roles::HttpRolesConfig;
+pub use self::roles::LdapRolesConfig;
+pub use self::roles::RolesBackend;
+pub use self::roles::StaticRolesConfig;
+pub use self::trusted_header::TrustedHeaderConfig;
 
 /// Supported audit record backends and their configuration options.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(tag = "backend")]
 pub enum AuditBackend {
     /// Emit audit records as log events.
@@ -26,16 +59,11 @@ pub enum AuditBackend {
     MongoDB(MongoDBAuditConfig),
 
     /// Drop all audit records.
+    #[default]
     #[serde(rename = "noop")]
     Noop,
 }
 
-impl Default for AuditBackend {
-    fn default() -> AuditBackend {
-        AuditBackend::Noop
-    }
-}
-
 /// Supported authenticators and their configuration options.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "backend")]
@@ -45,9 +73,60 @@ pub enum AuthenticatorBackend {
     #[serde(rename = "allow-all")]
     AllowAll,
 
+    /// Authenticate requests with static API keys (application passwords).
+    #[serde(rename = "api-key")]
+    ApiKey(ApiKeyConfig),
+
+    /// Authenticate clients with certificate (mTLS) details forwarded by the HTTPS proxy.
+    #[serde(rename = "client-cert")]
+    ClientCert(ClientCertConfig),
+
+    /// Authenticate requests by forwarding them to a generic HTTP authentication service.
+    #[serde(rename = "forward-auth")]
+    ForwardAuth(ForwardAuthConfig),
+
+    /// Authenticate users with HTTP Basic credentials checked against an htpasswd file.
+    #[serde(rename = "htpasswd")]
+    Htpasswd(HtpasswdConfig),
+
+    /// Authenticate opaque bearer tokens with OAuth2 token introspection (RFC 7662).
+    #[serde(rename = "introspection")]
+    Introspection(IntrospectionConfig),
+
+    /// Authenticate requests with JWT bearer tokens validated against a JSON Web Key Set.
+    #[serde(rename = "jwt")]
+    Jwt(JwtConfig),
+
+    /// Authenticate users with HTTP Basic credentials checked by binding to an LDAP directory.
+    #[serde(rename = "ldap")]
+    Ldap(LdapConfig),
+
     /// Authenticate users with [oauth2_proxy](https://oauth2-proxy.github.io/oauth2-proxy/).
     #[serde(rename = "oauth2-proxy")]
     OAuth2Proxy(OAuth2ProxyConfig),
+
+    /// Trust identity headers set by an upstream identity layer (like Cloudflare Access).
+    #[serde(rename = "trusted-header")]
+    TrustedHeader(TrustedHeaderConfig),
+}
+
+impl AuthenticatorBackend {
+    /// Name of the backend as it appears in the configuration.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            #[cfg(debug_assertions)]
+            AuthenticatorBackend::AllowAll => "allow-all",
+            AuthenticatorBackend::ApiKey(_) => "api-key",
+            AuthenticatorBackend::ClientCert(_) => "client-cert",
+            AuthenticatorBackend::ForwardAuth(_) => "forward-auth",
+            AuthenticatorBackend::Htpasswd(_) => "htpasswd",
+            AuthenticatorBackend::Introspection(_) => "introspection",
+            AuthenticatorBackend::Jwt(_) => "jwt",
+            AuthenticatorBackend::Ldap(_) => "ldap",
+            AuthenticatorBackend::OAuth2Proxy(_) => "oauth2-proxy",
+            AuthenticatorBackend::TrustedHeader(_) => "trusted-header",
+        }
+    }
 }
 
 /// Authenticator configuration and backend options.
@@ -57,6 +136,12 @@ pub struct AuthenticatorConfig {
     #[serde(flatten)]
     pub backend: AuthenticatorBackend,
 
+    /// Name of the authenticator reported in audit records.
+    ///
+    /// Defaults to the backend name and must be unique when authenticators are chained.
+    #[serde(default)]
+    pub name: Option<String>,
+
     /// If the authenticator returns a user ID, return it in this response header.
     #[serde(default = "AuthenticatorConfig::default_user_id_header")]
     pub user_id_header: String,
@@ -66,6 +151,61 @@ impl AuthenticatorConfig {
     pub fn default_user_id_header() -> String {
         "x-auth-request-user".into()
     }
+
+    /// Name of the authenticator reported in audit records.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or_else(|| self.backend.kind())
+    }
+}
+
+/// A single authenticator or a list of authenticators to try in order.
+#[derive(Clone, Debug, Serialize)]
+#[serde(transparent)]
+pub struct AuthenticatorChainConfig(pub Vec<AuthenticatorConfig>);
+
+impl<'de> Deserialize<'de> for AuthenticatorChainConfig {
+    fn deserialize<D>(deserializer: D) -> Result<AuthenticatorChainConfig, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OneOrMany;
+
+        impl<'de> Visitor<'de> for OneOrMany {
+            type Value = AuthenticatorChainConfig;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("an authenticator or a list of authenticators")
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let config = AuthenticatorConfig::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(AuthenticatorChainConfig(vec![config]))
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let configs = Vec::deserialize(SeqAccessDeserializer::new(seq))?;
+                Ok(AuthenticatorChainConfig(configs))
+            }
+        }
+
+        deserializer.deserialize_any(OneOrMany)
+    }
+}
+
+/// Select a named authenticator for requests matching some conditions.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuthenticatorSelection {
+    /// Name of the authenticator, from the `authenticators` option, to check requests with.
+    pub authenticator: String,
+
+    /// Match requests to check with the selected authenticator.
+    pub matches: RuleMatches,
 }
 
 /// AuthGateway configuration options.
@@ -76,7 +216,19 @@ pub struct Config {
     pub audit: AuditBackend,
 
     /// Configure the Authentication proxy to use.
-    pub authenticator: AuthenticatorConfig,
+    ///
+    /// When a list of authenticators is given they are tried in order until one allows the request.
+    pub authenticator: AuthenticatorChainConfig,
+
+    /// Select named authenticators for specific requests, in order.
+    ///
+    /// Requests not matching any selection are checked with the default `authenticator`.
+    #[serde(default)]
+    pub authenticator_selection: Vec<AuthenticatorSelection>,
+
+    /// Additional authenticators, by name, that requests can be checked with.
+    #[serde(default)]
+    pub authenticators: HashMap<String, AuthenticatorChainConfig>,
 
     /// Bind address for the HTTP server, in the format `address:port`.
     #[serde(default = "Config::default_bind")]
@@ -90,6 +242,10 @@ pub struct Config {
     #[serde(default)]
     pub request_extraction: RequestExtraction,
 
+    /// Configure the backend to lookup the roles of authenticated users with.
+    #[serde(default)]
+    pub roles: RolesBackend,
+
     /// List of files to load advanced rules from.
     #[serde(default)]
     pub rule_files: Vec<String>,
@@ -118,7 +274,7 @@ impl Config {
 }
 
 /// Serialize and Deserialize copy of log::LevelFilter.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub enum LevelFilter {
     /// A level lower than all log levels.
     #[serde(rename = "off")]
@@ -133,6 +289,7 @@ pub enum LevelFilter {
     Warn,
 
     /// Corresponds to the `Info` log level.
+    #[default]
     #[serde(rename = "info")]
     Info,
 
@@ -145,12 +302,6 @@ pub enum LevelFilter {
     Trace,
 }
 
-impl Default for LevelFilter {
-    fn default() -> LevelFilter {
-        LevelFilter::Info
-    }
-}
-
 impl From<LevelFilter> for log::LevelFilter {
     fn from(filter: LevelFilter) -> log::LevelFilter {
         match filter {
@@ -167,14 +318,36 @@ impl From<LevelFilter> for log::LevelFilter {
 /// Configure extraction of original request from check request headers.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RequestExtraction {
+    /// Header to extract the client IP address from (like `X-Real-IP`).
+    ///
+    /// The HTTPS proxy MUST overwrite this header or clients can spoof their address.
+    /// Ignored when `trusted_proxies` is set.
+    #[serde(default = "RequestExtraction::default_client_ip")]
+    pub client_ip: String,
+
+    /// Header listing the addresses of the client and the proxies the request went through.
+    #[serde(default = "RequestExtraction::default_forwarded_for")]
+    pub forwarded_for: String,
+
     /// Header to extract the original `Host` header from.
     #[serde(default = "RequestExtraction::default_host")]
     pub host: String,
 
+    /// Header to extract the original request method from, if the HTTPS proxy sets it.
+    #[serde(default = "RequestExtraction::default_method")]
+    pub method: String,
+
     /// Header to extract the original protocol (HTTP/HTTPS) from.
     #[serde(default = "RequestExtraction::default_protocol")]
     pub protocol: String,
 
+    /// Number of trusted proxies adding addresses to the `forwarded_for` header, if any.
+    ///
+    /// When set, the client IP address is the one added by the furthest trusted proxy:
+    /// the Nth address from the end of the `forwarded_for` header.
+    #[serde(default)]
+    pub trusted_proxies: Option<usize>,
+
     /// Header to extract the original request URI from.
     #[serde(default = "RequestExtraction::default_uri")]
     pub uri: String,
@@ -183,18 +356,34 @@ pub struct RequestExtraction {
 impl Default for RequestExtraction {
     fn default() -> RequestExtraction {
         RequestExtraction {
+            client_ip: RequestExtraction::default_client_ip(),
+            forwarded_for: RequestExtraction::default_forwarded_for(),
             host: RequestExtraction::default_host(),
+            method: RequestExtraction::default_method(),
             protocol: RequestExtraction::default_protocol(),
+            trusted_proxies: None,
             uri: RequestExtraction::default_uri(),
         }
     }
 }
 
 impl RequestExtraction {
+    fn default_client_ip() -> String {
+        "X-Real-IP".into()
+    }
+
+    fn default_forwarded_for() -> String {
+        "X-Forwarded-For".into()
+    }
+
     fn default_host() -> String {
         "Host".into()
     }
 
+    fn default_method() -> String {
+        "X-Original-Method".into()
+    }
+
     fn default_protocol() -> String {
         "X-Forwarded-Proto".into()
     }
@@ -203,3 +392,78 @@ impl RequestExtraction {
         "X-Original-URI".into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn authenticator_chain() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            authenticator:
+              - backend: api-key
+                name: bots
+              - backend: oauth2-proxy
+            "#,
+        )
+        .unwrap();
+        let names: Vec<&str> = config.authenticator.0.iter().map(|a| a.name()).collect();
+        assert_eq!(names, vec!["bots", "oauth2-proxy"]);
+    }
+
+    #[test]
+    fn authenticator_selection() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            authenticator:
+              backend: oauth2-proxy
+            authenticators:
+              api:
+                - backend: api-key
+                - backend: htpasswd
+                  file: htpasswd
+            authenticator_selection:
+              - authenticator: api
+                matches:
+                  domain: ['api.example.com']
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.authenticators["api"].0.len(), 2);
+        assert_eq!(config.authenticator_selection[0].authenticator, "api");
+    }
+
+    #[test]
+    fn authenticator_single() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            authenticator:
+              backend: oauth2-proxy
+              user_id_header: x-user
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.authenticator.0.len(), 1);
+        assert_eq!(config.authenticator.0[0].name(), "oauth2-proxy");
+        assert_eq!(config.authenticator.0[0].user_id_header, "x-user");
+    }
+
+    #[test]
+    fn roles_backend() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            authenticator:
+              backend: oauth2-proxy
+            roles:
+              backend: static
+              roles: ['sre']
+            "#,
+        )
+        .unwrap();
+        match config.roles {
+            super::RolesBackend::Static(config) => assert_eq!(config.roles, vec!["sre"]),
+            backend => panic!("unexpected roles backend {:?}", backend),
+        }
+    }
+}
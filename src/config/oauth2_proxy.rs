@@ -23,6 +23,16 @@ pub struct OAuth2ProxyConfig {
     )]
     pub prefix: String,
 
+    /// OAuth2Proxy response headers to copy into AuthGateway responses.
+    ///
+    /// OAuth2Proxy refreshes sessions by returning `Set-Cookie` headers, which NGINX can
+    /// forward to clients with `auth_request_set`.
+    #[serde(
+        default = "OAuth2ProxyConfig::default_response_headers",
+        rename = "response-headers"
+    )]
+    pub response_headers: Vec<String>,
+
     /// Timeout (in seconds) to wait for OAuth2Proxy to respond.
     ///
     /// By default this is an aggressive timeout as it applies to every request that is checked
@@ -47,25 +57,28 @@ impl OAuth2ProxyConfig {
         "/oauth2".into()
     }
 
+    fn default_response_headers() -> Vec<String> {
+        vec!["set-cookie".into()]
+    }
+
     fn default_timeout_sec() -> u64 {
         5
     }
 }
 
 /// Header in Oauth2Proxy responses to fetch user IDs from.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub enum OAuth2ProxyUserIdSourceHeader {
     /// Use the user email from X-Auth-Request-Email.
     #[serde(rename = "email")]
     Email,
 
+    /// Use the preferred username from X-Auth-Request-Preferred-Username.
+    #[serde(rename = "preferred-username")]
+    PreferredUsername,
+
     /// Use the user id from X-Auth-Request-User.
+    #[default]
     #[serde(rename = "user")]
     User,
 }
-
-impl Default for OAuth2ProxyUserIdSourceHeader {
-    fn default() -> OAuth2ProxyUserIdSourceHeader {
-        OAuth2ProxyUserIdSourceHeader::User
-    }
-}
@@ -0,0 +1,180 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Supported user roles lookup backends and their configuration options.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(tag = "backend")]
+pub enum RolesBackend {
+    /// Load roles from a YAML or JSON file mapping user IDs to lists of roles.
+    #[serde(rename = "file")]
+    File(FileRolesConfig),
+
+    /// Request roles from an HTTP endpoint.
+    #[serde(rename = "http")]
+    Http(HttpRolesConfig),
+
+    /// Map the LDAP groups users are members of to roles.
+    #[serde(rename = "ldap")]
+    Ldap(LdapRolesConfig),
+
+    /// Do not lookup user roles.
+    #[default]
+    #[serde(rename = "noop")]
+    Noop,
+
+    /// Assign the same list of roles to all users.
+    #[serde(rename = "static")]
+    Static(StaticRolesConfig),
+}
+
+/// File roles lookup backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FileRolesConfig {
+    /// Path to the YAML or JSON file mapping user IDs to lists of roles.
+    pub path: String,
+}
+
+/// HTTP roles lookup backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HttpRolesConfig {
+    /// Time (in seconds) to cache the roles of each user for.
+    ///
+    /// Set to 0 to request roles from the endpoint on every request.
+    #[serde(
+        default = "HttpRolesConfig::default_cache_ttl_sec",
+        rename = "cache-ttl-sec"
+    )]
+    pub cache_ttl_sec: u64,
+
+    /// Timeout (in seconds) to wait for the roles endpoint to respond.
+    #[serde(
+        default = "HttpRolesConfig::default_timeout_sec",
+        rename = "timeout-sec"
+    )]
+    pub timeout_sec: u64,
+
+    /// URL of the roles endpoint, `{user}` is replaced by the (URL encoded) user ID.
+    ///
+    /// The endpoint must respond with a JSON object with a `roles` list of strings.
+    pub url: String,
+}
+
+impl HttpRolesConfig {
+    fn default_cache_ttl_sec() -> u64 {
+        60
+    }
+
+    fn default_timeout_sec() -> u64 {
+        5
+    }
+}
+
+/// LDAP group membership roles lookup backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LdapRolesConfig {
+    /// Base DN to search users under.
+    #[serde(rename = "base-dn")]
+    pub base_dn: String,
+
+    /// DN to bind as to search for users and groups.
+    ///
+    /// The search is performed anonymously if no DN is set.
+    #[serde(default, rename = "bind-dn")]
+    pub bind_dn: Option<String>,
+
+    /// Password of the `bind-dn` user.
+    #[serde(default, rename = "bind-password")]
+    pub bind_password: Option<String>,
+
+    /// Time (in seconds) to cache the roles of each user for.
+    ///
+    /// Set to 0 to search the directory on every request.
+    #[serde(
+        default = "LdapRolesConfig::default_cache_ttl_sec",
+        rename = "cache-ttl-sec"
+    )]
+    pub cache_ttl_sec: u64,
+
+    /// Filter to search for the user with, `{user}` is replaced by the (escaped) user ID.
+    #[serde(default = "LdapRolesConfig::default_filter")]
+    pub filter: String,
+
+    /// Base DN to search groups under.
+    ///
+    /// When set, groups are found with the `group-filter` search instead of
+    /// the `member-attribute` of the user entry.
+    #[serde(default, rename = "group-base-dn")]
+    pub group_base_dn: Option<String>,
+
+    /// Filter to search for the user's groups with.
+    ///
+    /// `{dn}` is replaced by the (escaped) user DN and `{user}` by the (escaped) user ID.
+    #[serde(
+        default = "LdapRolesConfig::default_group_filter",
+        rename = "group-filter"
+    )]
+    pub group_filter: String,
+
+    /// Attribute of the user entry listing the DNs of the groups the user is a member of.
+    #[serde(
+        default = "LdapRolesConfig::default_member_attribute",
+        rename = "member-attribute"
+    )]
+    pub member_attribute: String,
+
+    /// Regular expression to map group DNs to roles, group DNs not matching are ignored.
+    ///
+    /// The role is the first capture group, or the entire match if the expression has none.
+    #[serde(
+        default = "LdapRolesConfig::default_role_pattern",
+        rename = "role-pattern"
+    )]
+    pub role_pattern: String,
+
+    /// Upgrade `ldap://` connections to TLS with the StartTLS operation.
+    #[serde(default)]
+    pub starttls: bool,
+
+    /// Timeout (in seconds) to wait for the directory to respond to each operation.
+    #[serde(
+        default = "LdapRolesConfig::default_timeout_sec",
+        rename = "timeout-sec"
+    )]
+    pub timeout_sec: u64,
+
+    /// URL of the directory to connect to (`ldap://` or `ldaps://`).
+    pub url: String,
+}
+
+impl LdapRolesConfig {
+    fn default_cache_ttl_sec() -> u64 {
+        300
+    }
+
+    fn default_filter() -> String {
+        "(uid={user})".into()
+    }
+
+    fn default_group_filter() -> String {
+        "(member={dn})".into()
+    }
+
+    fn default_member_attribute() -> String {
+        "memberOf".into()
+    }
+
+    fn default_role_pattern() -> String {
+        "(?i)^cn=([^,]+)".into()
+    }
+
+    fn default_timeout_sec() -> u64 {
+        5
+    }
+}
+
+/// Static roles lookup backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StaticRolesConfig {
+    /// Roles assigned to all authenticated users.
+    pub roles: Vec<String>,
+}
@@ -0,0 +1,47 @@
+use ipnet::IpNet;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Trusted upstream identity header backend configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TrustedHeaderConfig {
+    /// Request header with the user email set by the upstream identity layer.
+    #[serde(default, rename = "email-header")]
+    pub email_header: Option<String>,
+
+    /// Request header with the list of user groups set by the upstream identity layer.
+    #[serde(default, rename = "groups-header")]
+    pub groups_header: Option<String>,
+
+    /// Separator between groups in the groups request header.
+    #[serde(
+        default = "TrustedHeaderConfig::default_groups_separator",
+        rename = "groups-separator"
+    )]
+    pub groups_separator: String,
+
+    /// Shared secret the upstream identity layer sends in the `secret-header`.
+    #[serde(default)]
+    pub secret: Option<String>,
+
+    /// Request header with the shared secret proving the request comes from the upstream.
+    #[serde(default, rename = "secret-header")]
+    pub secret_header: Option<String>,
+
+    /// Trust identity headers on requests from upstreams in these IP ranges.
+    ///
+    /// Ranges are matched against the client IP address extracted from the request
+    /// as configured in `request_extraction`.
+    #[serde(default, rename = "trusted-cidrs")]
+    pub trusted_cidrs: Vec<IpNet>,
+
+    /// Request header with the user ID set by the upstream identity layer.
+    #[serde(rename = "user-header")]
+    pub user_header: String,
+}
+
+impl TrustedHeaderConfig {
+    fn default_groups_separator() -> String {
+        ",".into()
+    }
+}
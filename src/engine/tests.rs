@@ -14,6 +14,8 @@ use crate::models::PostAuthRule;
 use crate::models::PreAuthRule;
 use crate::models::RequestContext;
 use crate::models::RuleAction;
+use crate::models::RuleAny;
+use crate::models::RuleDomains;
 use crate::models::RuleMatches;
 use crate::models::RuleSessionMatches;
 
@@ -33,6 +35,14 @@ fn build_no_rules() {
     assert_eq!(engine.rules_preauth, vec![]);
 }
 
+#[test]
+fn build_invalid_template() {
+    let engine = RulesEngine::builder()
+        .rule_files(&[String::from("tests/fixtures/rules_invalid_template.yaml")])
+        .build();
+    assert!(engine.is_err());
+}
+
 #[test]
 fn build_one_source() {
     let engine = RulesEngine::builder()
@@ -50,12 +60,14 @@ fn build_one_source() {
             },
             headers_set: HashMap::default(),
             matches: Some(RuleMatches {
-                any: true,
-                domain: HashSet::default(),
+                any: RuleAny::Bool(true),
+                domain: Default::default(),
                 header_equal: HashMap::default(),
                 uri: HashSet::default(),
+                ..Default::default()
             }),
             session_matches: None,
+            when: None,
         }]
     );
     assert_eq!(
@@ -69,8 +81,10 @@ fn build_one_source() {
                     let mut set = HashSet::new();
                     set.insert("some@email.com".to_string());
                     set
-                }
+                },
+                ..Default::default()
             }),
+            when: None,
         }]
     );
     assert_eq!(
@@ -78,15 +92,12 @@ fn build_one_source() {
         vec![PreAuthRule {
             action: RuleAction::Allow,
             matches: RuleMatches {
-                any: false,
-                domain: {
-                    let mut set = HashSet::new();
-                    set.insert("example.com".to_string());
-                    set
-                },
+                domain: RuleDomains::from_static(&["example.com"]),
                 header_equal: HashMap::default(),
                 uri: HashSet::default(),
-            }
+                ..Default::default()
+            },
+            when: None,
         }]
     );
 }
@@ -131,14 +142,16 @@ fn eval_enrich_rule_does_not_match() {
             },
             headers_set: {
                 let mut map = HashMap::new();
-                map.insert("X-Test".to_string(), "set".to_string());
+                map.insert("X-Test".to_string(), "set".parse().unwrap());
                 map
             },
             matches: None,
             session_matches: Some(RuleSessionMatches {
                 authenticated: Some(true),
                 user: Default::default(),
+                ..Default::default()
             }),
+            when: None,
         })
         .build()
         .unwrap();
@@ -168,14 +181,16 @@ fn eval_enrich_rule_matches() {
             },
             headers_set: {
                 let mut map = HashMap::new();
-                map.insert("X-Test".to_string(), "set".to_string());
+                map.insert("X-Test".to_string(), "set".parse().unwrap());
                 map
             },
             matches: None,
             session_matches: Some(RuleSessionMatches {
                 authenticated: Some(false),
                 user: Default::default(),
+                ..Default::default()
             }),
+            when: None,
         })
         .build()
         .unwrap();
@@ -190,6 +205,46 @@ fn eval_enrich_rule_matches() {
     assert_eq!(actual, "set");
 }
 
+#[test]
+fn eval_enrich_rule_templates() {
+    let extraction = RequestExtraction::default();
+    let request = test_request("domain", "/path/to/page").to_http_request();
+    let context = RequestContext::from_request(&request, &extraction).unwrap();
+    let engine = RulesEngine::builder()
+        .rule_enrich(EnrichResponseRule {
+            headers_remove: Default::default(),
+            headers_set: {
+                let mut map = HashMap::new();
+                map.insert("X-Auth-Roles".to_string(), "{{roles}}".parse().unwrap());
+                map.insert("X-Auth-User".to_string(), "{{ user }}".parse().unwrap());
+                map
+            },
+            matches: None,
+            session_matches: Some(RuleSessionMatches {
+                authenticated: Some(true),
+                user: Default::default(),
+                ..Default::default()
+            }),
+            when: None,
+        })
+        .build()
+        .unwrap();
+    let mut result = AuthenticationResult::allowed();
+    result.authentication_context.authenticated = true;
+    result.authentication_context.roles = vec!["admin".into(), "sre".into()];
+    result.authentication_context.user = Some("alice".into());
+    let result = engine.eval_enrich(&context, result).unwrap();
+    let roles = result
+        .headers
+        .get("x-auth-roles")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let user = result.headers.get("x-auth-user").unwrap().to_str().unwrap();
+    assert_eq!(roles, "admin,sre");
+    assert_eq!(user, "alice");
+}
+
 #[test]
 fn eval_postauth_no_rules() {
     let extraction = RequestExtraction::default();
@@ -207,6 +262,9 @@ fn eval_postauth_rule_allow() {
     let request = test_request("domain", "/path/to/page").to_http_request();
     let auth_context = AuthenticationContext {
         authenticated: true,
+        attributes: Default::default(),
+        groups: Default::default(),
+        roles: Default::default(),
         user: None,
         session: None,
     };
@@ -218,7 +276,9 @@ fn eval_postauth_rule_allow() {
             session_matches: Some(RuleSessionMatches {
                 authenticated: Some(false),
                 user: Default::default(),
+                ..Default::default()
             }),
+            when: None,
         })
         .rule_postauth(PostAuthRule {
             action: RuleAction::Allow,
@@ -226,7 +286,9 @@ fn eval_postauth_rule_allow() {
             session_matches: Some(RuleSessionMatches {
                 authenticated: Some(true),
                 user: Default::default(),
+                ..Default::default()
             }),
+            when: None,
         })
         .build()
         .unwrap();
@@ -240,6 +302,9 @@ fn eval_postauth_rule_does_not_match() {
     let request = test_request("domain", "/path/to/page").to_http_request();
     let auth_context = AuthenticationContext {
         authenticated: true,
+        attributes: Default::default(),
+        groups: Default::default(),
+        roles: Default::default(),
         user: None,
         session: None,
     };
@@ -251,7 +316,9 @@ fn eval_postauth_rule_does_not_match() {
             session_matches: Some(RuleSessionMatches {
                 authenticated: Some(false),
                 user: Default::default(),
+                ..Default::default()
             }),
+            when: None,
         })
         .build()
         .unwrap();
@@ -281,7 +348,6 @@ fn eval_preauth_rule_allow() {
         .rule_preauth(PreAuthRule {
             action: RuleAction::Deny,
             matches: RuleMatches {
-                any: false,
                 domain: Default::default(),
                 header_equal: {
                     let mut map = HashMap::new();
@@ -292,12 +358,13 @@ fn eval_preauth_rule_allow() {
                     map
                 },
                 uri: Default::default(),
+                ..Default::default()
             },
+            when: None,
         })
         .rule_preauth(PreAuthRule {
             action: RuleAction::Allow,
             matches: RuleMatches {
-                any: false,
                 domain: Default::default(),
                 header_equal: {
                     let mut map = HashMap::new();
@@ -308,7 +375,9 @@ fn eval_preauth_rule_allow() {
                     map
                 },
                 uri: Default::default(),
+                ..Default::default()
             },
+            when: None,
         })
         .build()
         .unwrap();
@@ -325,18 +394,66 @@ fn eval_preauth_rule_does_not_match() {
         .rule_preauth(PreAuthRule {
             action: RuleAction::Deny,
             matches: RuleMatches {
-                any: false,
-                domain: {
-                    let mut set = HashSet::default();
-                    set.insert("not.me".to_string());
-                    set
-                },
+                domain: RuleDomains::from_static(&["not.me"]),
                 header_equal: Default::default(),
                 uri: Default::default(),
+                ..Default::default()
             },
+            when: None,
         })
         .build()
         .unwrap();
     let action = engine.eval_preauth(&context);
     assert_eq!(action, RuleAction::Delegate);
 }
+
+#[test]
+fn eval_preauth_rule_expired() {
+    let extraction = RequestExtraction::default();
+    let request = test_request("domain", "/path/to/page").to_http_request();
+    let context = RequestContext::from_request(&request, &extraction).unwrap();
+    let rule: PreAuthRule = serde_yaml::from_str(
+        r#"
+        action: allow
+        matches:
+          domain: ['domain']
+        when:
+          not_after: '2022-03-31T18:00:00Z'
+        "#,
+    )
+    .unwrap();
+    let engine = RulesEngine::builder().rule_preauth(rule).build().unwrap();
+    let action = engine.eval_preauth(&context);
+    assert_eq!(action, RuleAction::Delegate);
+}
+
+#[test]
+fn eval_preauth_rule_path_traversal() {
+    let rule = |condition: &str| -> PreAuthRule {
+        let rule = format!(
+            "{{action: allow, matches: {{{}: ['/public/']}}}}",
+            condition
+        );
+        serde_yaml::from_str(&rule).unwrap()
+    };
+    let extraction = RequestExtraction::default();
+    for uri in &[
+        "/public/../admin",
+        "/public/%2e%2e/admin",
+        "/public/%2E%2E/admin?x=1",
+    ] {
+        let request = test_request("domain", uri).to_http_request();
+        let context = RequestContext::from_request(&request, &extraction).unwrap();
+        let engine = RulesEngine::builder()
+            .rule_preauth(rule("path_prefix"))
+            .build()
+            .unwrap();
+        assert_eq!(engine.eval_preauth(&context), RuleAction::Delegate);
+        // Raw URI conditions are fooled by traversals, so they must not be used for paths.
+        let engine = RulesEngine::builder()
+            .rule_preauth(rule("uri_prefix"))
+            .build()
+            .unwrap();
+        assert_eq!(engine.eval_preauth(&context), RuleAction::Allow);
+    }
+}
@@ -66,6 +66,9 @@ pub enum InvalidAuthRequest {
     #[error("Host header is not UTF8 encoded")]
     HostNotUtf8,
 
+    #[error("X-Original-Method header is not UTF8 encoded")]
+    MethodNotUtf8,
+
     #[error("Required Host header is missing")]
     NoHost,
 
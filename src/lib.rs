@@ -38,7 +38,7 @@ pub async fn run() -> Result<()> {
     builder.filter_level(config.log_level.clone().into()).init();
 
     // Configure audit reporter and authenticator proxy.
-    let authenticator = Authenticator::factory(&config)?;
+    let authenticator = Authenticator::factory(&config).await?;
     let auditor = Auditor::factory(config.audit).await?;
     let request_extraction = config.request_extraction;
 
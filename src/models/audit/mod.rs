@@ -1,3 +1,4 @@
+use std::net::IpAddr;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -44,6 +45,10 @@ pub enum AuditReason {
     /// The request was denied by a pre-auth phase rule.
     #[serde(rename = "pre-auth-denied")]
     PreAuthDenied,
+
+    /// The request was denied because the roles of the user could not be looked up.
+    #[serde(rename = "roles-lookup-failed")]
+    RolesLookupFailed,
 }
 
 /// Record of information about an authorisation request for auditing.
@@ -52,9 +57,18 @@ pub struct AuditRecord {
     /// The request was ultimatelly allowed.
     pub authenticated: bool,
 
+    /// Name of the authenticator that checked the request, if any.
+    pub authenticator: Option<String>,
+
+    /// IP address of the client that sent the request, if available.
+    pub client_ip: Option<IpAddr>,
+
     /// Duration of the authentication request processing.
     pub duration: Duration,
 
+    /// HTTP method of the original request, if provided by the HTTPS proxy.
+    pub method: Option<String>,
+
     /// Protocol the original request was sent over.
     pub protocol: RequestProtocol,
 
@@ -64,6 +78,9 @@ pub struct AuditRecord {
     /// Full URL looking for authentication.
     pub resource: String,
 
+    /// Roles assigned to the user attached to the request.
+    pub roles: Vec<String>,
+
     /// Exact authentication status returned to the client.
     pub result: AuthenticationStatus,
 
@@ -80,6 +97,8 @@ pub struct AuditRecord {
 /// Collect request and processing information to build an AuditRecord.
 #[derive(Debug)]
 pub struct AuditRecordBuilder {
+    client_ip: Option<IpAddr>,
+    method: Option<String>,
     protocol: RequestProtocol,
     resource: String,
     std_start: Instant,
@@ -91,10 +110,14 @@ impl AuditRecordBuilder {
     pub fn finish(self, result: &AuthenticationResult) -> AuditRecord {
         AuditRecord {
             authenticated: result.status.authenticated(),
+            authenticator: result.authenticator.clone(),
+            client_ip: self.client_ip,
             duration: self.std_start.elapsed(),
+            method: self.method,
             protocol: self.protocol,
             reason: result.audit_reason,
             resource: self.resource,
+            roles: result.authentication_context.roles.clone(),
             result: result.status,
             session_id: result.authentication_context.session.clone(),
             timestamp: self.timestamp,
@@ -107,6 +130,8 @@ impl AuditRecordBuilder {
         let protocol = context.protocol.clone();
         let resource = format!("{0}://{1}{2}", protocol, context.host, context.uri);
         AuditRecordBuilder {
+            client_ip: context.client_ip,
+            method: context.method.map(String::from),
             protocol,
             resource,
             std_start: Instant::now(),
@@ -9,19 +9,27 @@ use super::AuditRecordBuilder;
 #[test]
 fn finish_audit_record() {
     let context = RequestContext {
+        client_ip: None,
         headers: Default::default(),
         host: "not.me",
+        method: None,
+        path: "/path/to/nowhere".into(),
         protocol: RequestProtocol::Https,
-        uri: &"/path/to/nowhere",
+        query: Default::default(),
+        uri: "/path/to/nowhere",
     };
     let result = AuthenticationResult::from_status(AuthenticationStatus::MustLogin);
     let audit = AuditRecordBuilder::start(&context);
     let audit = audit.finish(&result);
-    assert_eq!(audit.authenticated, false);
+    assert!(!audit.authenticated);
+    assert_eq!(audit.authenticator, None);
+    assert_eq!(audit.client_ip, None);
+    assert_eq!(audit.method, None);
     assert_eq!(audit.protocol, RequestProtocol::Https);
     assert_eq!(audit.reason, AuditReason::InvalidSession);
     assert_eq!(audit.resource, "https://not.me/path/to/nowhere");
     assert_eq!(audit.result, AuthenticationStatus::MustLogin);
+    assert!(audit.roles.is_empty());
     assert_eq!(audit.session_id, None);
     assert_eq!(audit.user_id, None);
 }
@@ -29,12 +37,18 @@ fn finish_audit_record() {
 #[test]
 fn start_audit_record() {
     let context = RequestContext {
+        client_ip: Some("192.0.2.1".parse().unwrap()),
         headers: Default::default(),
         host: "not.me",
+        method: Some("GET"),
+        path: "/path/to/nowhere".into(),
         protocol: RequestProtocol::Https,
-        uri: &"/path/to/nowhere",
+        query: Default::default(),
+        uri: "/path/to/nowhere",
     };
     let audit = AuditRecordBuilder::start(&context);
+    assert_eq!(audit.client_ip, Some("192.0.2.1".parse().unwrap()));
+    assert_eq!(audit.method, Some("GET".to_string()));
     assert_eq!(audit.protocol, RequestProtocol::Https);
     assert_eq!(audit.resource, "https://not.me/path/to/nowhere");
 }
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
 
 use actix_web::HttpRequest;
+use percent_encoding::percent_decode_str;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -13,6 +15,17 @@ pub struct AuthenticationContext {
     /// Result of the authenticator process for the request.
     pub authenticated: bool,
 
+    /// Additional user attributes provided by the authenticator, if any.
+    ///
+    /// The set of attributes depends on the selected authenticator.
+    pub attributes: HashMap<String, String>,
+
+    /// Groups the user is a member of, as provided by the authenticator.
+    pub groups: Vec<String>,
+
+    /// Roles assigned to the user, used to make authorization decisions.
+    pub roles: Vec<String>,
+
     /// User ID provided by the authenticator, if possible.
     ///
     /// The value a user ID takes depends on the selected authenticator.
@@ -31,6 +44,9 @@ impl AuthenticationContext {
     pub fn unauthenticated() -> AuthenticationContext {
         AuthenticationContext {
             authenticated: false,
+            attributes: HashMap::new(),
+            groups: Vec::new(),
+            roles: Vec::new(),
             user: None,
             session: None,
         }
@@ -40,16 +56,30 @@ impl AuthenticationContext {
 /// Rule evaluation context obtained from the request to authenticate.
 #[derive(Debug)]
 pub struct RequestContext<'request> {
+    /// IP address of the client that sent the request, if provided by the HTTPS proxy.
+    pub client_ip: Option<IpAddr>,
+
     /// HTTP headers extracted from the auth_request request.
     pub headers: HashMap<&'request str, Vec<&'request str>>,
 
     /// The host the request is for, as determined by the `Host` header.
     pub host: &'request str,
 
+    /// HTTP method of the request to authenticate, if provided by the HTTPS proxy.
+    pub method: Option<&'request str>,
+
+    /// Normalised path of the request to authenticate.
+    ///
+    /// The path is percent-decoded, with duplicate slashes and dot segments removed.
+    pub path: String,
+
     /// Protocol of the request to authenticate.
     pub protocol: RequestProtocol,
 
-    /// URI of the request to authenticate.
+    /// Percent-decoded query string parameters of the request to authenticate.
+    pub query: HashMap<String, Vec<String>>,
+
+    /// URI of the request to authenticate, as provided by the HTTPS proxy.
     pub uri: &'request str,
 }
 
@@ -66,6 +96,13 @@ impl<'request> RequestContext<'request> {
             .ok_or(InvalidAuthRequest::NoHost)?;
         let host =
             std::str::from_utf8(host.as_bytes()).map_err(|_| InvalidAuthRequest::HostNotUtf8)?;
+        let method = match request.headers().get(&extraction.method) {
+            None => None,
+            Some(method) => Some(
+                std::str::from_utf8(method.as_bytes())
+                    .map_err(|_| InvalidAuthRequest::MethodNotUtf8)?,
+            ),
+        };
         let protocol = request
             .headers()
             .get(&extraction.protocol)
@@ -84,6 +121,12 @@ impl<'request> RequestContext<'request> {
             .ok_or(InvalidAuthRequest::NoUri)?;
         let uri =
             std::str::from_utf8(uri.as_bytes()).map_err(|_| InvalidAuthRequest::UriNotUtf8)?;
+        let (path, query) = match uri.split_once('?') {
+            None => (uri, ""),
+            Some((path, query)) => (path, query),
+        };
+        let path = normalise_path(path);
+        let query = parse_query(query);
 
         // Convert request headers into a HashMap.
         let mut headers: HashMap<&'request str, Vec<&'request str>> = HashMap::new();
@@ -95,14 +138,92 @@ impl<'request> RequestContext<'request> {
         }
 
         // Build a request context for rules evaluation.
+        let client_ip = RequestContext::client_ip(request, extraction);
         let context = crate::models::RequestContext {
+            client_ip,
             headers,
             host,
+            method,
+            path,
             protocol,
+            query,
             uri,
         };
         Ok(context)
     }
+
+    /// Extract the IP address of the client from the configured headers.
+    ///
+    /// Missing or invalid addresses are ignored so rules matching on them don't match.
+    fn client_ip(request: &HttpRequest, extraction: &RequestExtraction) -> Option<IpAddr> {
+        let (header, address) = match extraction.trusted_proxies {
+            None => {
+                let header = extraction.client_ip.as_str();
+                let address = request.headers().get(header)?.to_str().ok()?;
+                (header, address)
+            }
+            Some(hops) => {
+                let header = extraction.forwarded_for.as_str();
+                let mut addresses: Vec<&str> = Vec::new();
+                for value in request.headers().get_all(header) {
+                    addresses.extend(value.to_str().ok()?.split(','));
+                }
+                let index = addresses.len().checked_sub(hops)?;
+                (header, *addresses.get(index)?)
+            }
+        };
+        match address.trim().parse() {
+            Ok(address) => Some(address),
+            Err(_) => {
+                log::warn!("Invalid IP address `{}` in header {}", address, header);
+                None
+            }
+        }
+    }
+}
+
+/// Percent-decode a request path and resolve dot segments and duplicate slashes.
+///
+/// The path is split into segments before decoding them so encoded slashes (`%2F`) are
+/// part of a segment, like upstreams treat them, and are kept encoded in the result.
+/// Dot segments can't move the path above the root and trailing slashes are preserved.
+fn normalise_path(path: &str) -> String {
+    let mut segments: Vec<String> = Vec::new();
+    let mut trailing_slash = false;
+    for segment in path.split('/') {
+        let segment = percent_decode_str(segment)
+            .decode_utf8_lossy()
+            .replace('/', "%2F");
+        trailing_slash = matches!(segment.as_str(), "" | "." | "..");
+        match segment.as_str() {
+            "" | "." => (),
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let mut normalised = format!("/{}", segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        normalised.push('/');
+    }
+    normalised
+}
+
+/// Parse a query string into percent-decoded parameters.
+///
+/// Parameters without a value (`?flag`) are set to an empty string.
+fn parse_query(query: &str) -> HashMap<String, Vec<String>> {
+    let decode = |value: &str| {
+        let value = value.replace('+', " ");
+        percent_decode_str(&value).decode_utf8_lossy().into_owned()
+    };
+    let mut params: HashMap<String, Vec<String>> = HashMap::new();
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let (name, value) = param.split_once('=').unwrap_or((param, ""));
+        params.entry(decode(name)).or_default().push(decode(value));
+    }
+    params
 }
 
 /// Protocol used to request the protcted resource.
@@ -127,3 +248,99 @@ impl std::fmt::Display for RequestProtocol {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::normalise_path;
+    use super::parse_query;
+    use super::RequestContext;
+    use crate::config::RequestExtraction;
+
+    fn client_ip(request: TestRequest, trusted_proxies: Option<usize>) -> Option<String> {
+        let request = request
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/"))
+            .to_http_request();
+        let extraction = RequestExtraction {
+            trusted_proxies,
+            ..Default::default()
+        };
+        let context = RequestContext::from_request(&request, &extraction).unwrap();
+        context.client_ip.map(|ip| ip.to_string())
+    }
+
+    #[test]
+    fn client_ip_forwarded_for() {
+        let request = || {
+            TestRequest::get()
+                .append_header(("X-Forwarded-For", "10.0.0.1, 192.0.2.10"))
+                .append_header(("X-Forwarded-For", "10.1.0.1"))
+                .append_header(("X-Real-IP", "10.1.0.1"))
+        };
+        assert_eq!(client_ip(request(), Some(1)), Some("10.1.0.1".into()));
+        assert_eq!(client_ip(request(), Some(2)), Some("192.0.2.10".into()));
+        assert_eq!(client_ip(request(), Some(4)), None);
+    }
+
+    #[test]
+    fn client_ip_invalid() {
+        let request = TestRequest::get().append_header(("X-Real-IP", "not-an-ip"));
+        assert_eq!(client_ip(request, None), None);
+    }
+
+    #[test]
+    fn normalise_paths() {
+        assert_eq!(normalise_path(""), "/");
+        assert_eq!(normalise_path("/health"), "/health");
+        assert_eq!(normalise_path("/a/../admin"), "/admin");
+        assert_eq!(normalise_path("//admin//users/"), "/admin/users/");
+        assert_eq!(normalise_path("/a/./b/.."), "/a/");
+        assert_eq!(normalise_path("/../../etc"), "/etc");
+        assert_eq!(normalise_path("/a/%2e%2e/%61dmin/users"), "/admin/users");
+        assert_eq!(normalise_path("/hello%20world"), "/hello world");
+    }
+
+    #[test]
+    fn normalise_paths_encoded_slashes() {
+        assert_eq!(normalise_path("/a%2F..%2Fadmin"), "/a%2F..%2Fadmin");
+        assert_eq!(normalise_path("/a%2f..%2fadmin"), "/a%2F..%2Fadmin");
+        assert_eq!(normalise_path("/admin%2Fusers/"), "/admin%2Fusers/");
+    }
+
+    #[test]
+    fn parse_queries() {
+        let query = parse_query("probe=1&tag=a&tag=b%26c&flag&q=x+y&");
+        assert_eq!(query.get("probe"), Some(&vec!["1".to_string()]));
+        assert_eq!(
+            query.get("tag"),
+            Some(&vec!["a".to_string(), "b&c".to_string()])
+        );
+        assert_eq!(query.get("flag"), Some(&vec!["".to_string()]));
+        assert_eq!(query.get("q"), Some(&vec!["x y".to_string()]));
+        assert_eq!(query.len(), 4);
+    }
+
+    #[test]
+    fn path_and_query() {
+        let request = TestRequest::get()
+            .append_header(("Host", "domain"))
+            .append_header(("X-Forwarded-Proto", "https"))
+            .append_header(("X-Original-URI", "/api/../health?probe=1"))
+            .to_http_request();
+        let context = RequestContext::from_request(&request, &Default::default()).unwrap();
+        assert_eq!(context.path, "/health");
+        assert_eq!(context.query.get("probe"), Some(&vec!["1".to_string()]));
+        assert_eq!(context.uri, "/api/../health?probe=1");
+    }
+
+    #[test]
+    fn client_ip_real_ip() {
+        let request = TestRequest::get()
+            .append_header(("X-Forwarded-For", "10.0.0.1"))
+            .append_header(("X-Real-IP", "2001:db8::1"));
+        assert_eq!(client_ip(request, None), Some("2001:db8::1".into()));
+    }
+}
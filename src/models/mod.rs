@@ -17,7 +17,12 @@ pub use rule::PostAuthRule;
 pub use rule::PreAuthRule;
 pub use rule::Rule;
 pub use rule::RuleAction;
+#[cfg(test)]
+pub use rule::RuleAny;
+#[cfg(test)]
+pub use rule::RuleDomains;
 pub use rule::RuleMatches;
+#[cfg(test)]
 pub use rule::RuleSessionMatches;
 
 /// Final outcome from the authentication process.
@@ -29,6 +34,9 @@ pub struct AuthenticationResult {
     /// Authentication context to match post-auth rules and to build authentication responses.
     pub authentication_context: AuthenticationContext,
 
+    /// Name of the authenticator that checked the request, if any.
+    pub authenticator: Option<String>,
+
     /// Set of headers from the authenticator to propagate back to the HTTP proxy.
     pub headers: HeaderMap,
 
@@ -42,6 +50,7 @@ impl AuthenticationResult {
         AuthenticationResult {
             audit_reason: AuditReason::Allowed,
             authentication_context: AuthenticationContext::unauthenticated(),
+            authenticator: None,
             headers: HeaderMap::new(),
             status: AuthenticationStatus::Allowed,
         }
@@ -52,6 +61,7 @@ impl AuthenticationResult {
         AuthenticationResult {
             audit_reason: AuditReason::Denied,
             authentication_context: AuthenticationContext::unauthenticated(),
+            authenticator: None,
             headers: HeaderMap::new(),
             status: AuthenticationStatus::Denied,
         }
@@ -67,6 +77,7 @@ impl AuthenticationResult {
         AuthenticationResult {
             audit_reason,
             authentication_context: AuthenticationContext::unauthenticated(),
+            authenticator: None,
             headers: HeaderMap::new(),
             status,
         }
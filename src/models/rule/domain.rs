@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::RuleRegex;
+
+/// Pattern matching many domains.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum DomainPattern {
+    /// Regular expression (`~^preview-[0-9]+\.example\.com$`).
+    Regex { regex: RuleRegex, source: String },
+
+    /// Subdomains of a domain (`*.example.com`) and, optionally, the domain itself (`.example.com`).
+    Suffix {
+        include_domain: bool,
+        source: String,
+        suffix: String,
+    },
+}
+
+impl DomainPattern {
+    fn is_match(&self, host: &str) -> bool {
+        match self {
+            DomainPattern::Regex { regex, .. } => regex.is_match(host),
+            DomainPattern::Suffix {
+                include_domain,
+                suffix,
+                ..
+            } => host.ends_with(suffix.as_str()) || (*include_domain && host == &suffix[1..]),
+        }
+    }
+
+    fn source(&self) -> String {
+        match self {
+            DomainPattern::Regex { source, .. } => source.clone(),
+            DomainPattern::Suffix { source, .. } => source.clone(),
+        }
+    }
+}
+
+/// Domains requests are matched against.
+///
+/// Domains are compared ignoring case and ports and can be:
+///
+/// * Exact domains (`app.example.com`), looked up in a set.
+/// * Wildcards matching subdomains (`*.example.com`).
+/// * Suffixes matching the domain and its subdomains (`.example.com`).
+/// * Regular expressions, prefixed with `~` (`~^preview-[0-9]+\.example\.com$`).
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(into = "Vec<String>", try_from = "Vec<String>")]
+pub struct RuleDomains {
+    exact: HashSet<String>,
+    patterns: Vec<DomainPattern>,
+}
+
+impl RuleDomains {
+    /// Check if the `Host` of a request matches any of the domains.
+    pub fn contains(&self, host: &str) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let host = strip_port(host).to_ascii_lowercase();
+        self.exact.contains(&host) || self.patterns.iter().any(|pattern| pattern.is_match(&host))
+    }
+
+    /// Create a set of domains from a list, panicking if any of them is invalid.
+    #[cfg(test)]
+    pub fn from_static(domains: &[&str]) -> RuleDomains {
+        let domains: Vec<String> = domains.iter().map(|domain| domain.to_string()).collect();
+        RuleDomains::try_from(domains).unwrap()
+    }
+
+    /// Check if no domains are set.
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.patterns.is_empty()
+    }
+}
+
+impl From<RuleDomains> for Vec<String> {
+    fn from(domains: RuleDomains) -> Vec<String> {
+        let mut sources: Vec<String> = domains.exact.into_iter().collect();
+        sources.sort();
+        sources.extend(domains.patterns.iter().map(DomainPattern::source));
+        sources
+    }
+}
+
+impl TryFrom<Vec<String>> for RuleDomains {
+    type Error = regex::Error;
+
+    fn try_from(sources: Vec<String>) -> Result<RuleDomains, regex::Error> {
+        let mut domains = RuleDomains::default();
+        for source in sources {
+            if let Some(regex) = source.strip_prefix('~') {
+                let regex = RuleRegex::try_from(format!("(?i){}", regex))?;
+                domains
+                    .patterns
+                    .push(DomainPattern::Regex { regex, source });
+                continue;
+            }
+            let domain = strip_port(&source).to_ascii_lowercase();
+            let pattern = match (domain.strip_prefix("*."), domain.starts_with('.')) {
+                (Some(domain), _) => Some((false, format!(".{}", domain))),
+                (None, true) => Some((true, domain.clone())),
+                (None, false) => None,
+            };
+            match pattern {
+                None => {
+                    domains.exact.insert(domain);
+                }
+                Some((include_domain, suffix)) => domains.patterns.push(DomainPattern::Suffix {
+                    include_domain,
+                    source,
+                    suffix,
+                }),
+            }
+        }
+        Ok(domains)
+    }
+}
+
+/// Remove the port, if any, from a `Host` value.
+fn strip_port(host: &str) -> &str {
+    // IPv6 addresses are wrapped in [] when a port is present.
+    if host.starts_with('[') {
+        return match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        };
+    }
+    match host.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::RuleDomains;
+
+    fn domains(domains: &[&str]) -> RuleDomains {
+        RuleDomains::from_static(domains)
+    }
+
+    #[test]
+    fn match_exact() {
+        let domains = domains(&["app.example.com"]);
+        assert!(domains.contains("app.example.com"));
+        assert!(domains.contains("App.Example.com:8443"));
+        assert!(!domains.contains("api.example.com"));
+    }
+
+    #[test]
+    fn match_ipv6_host() {
+        let domains = domains(&["[::1]"]);
+        assert!(domains.contains("[::1]:8080"));
+        assert!(domains.contains("[::1]"));
+    }
+
+    #[test]
+    fn match_regex() {
+        let domains = domains(&["~^preview-[0-9]+\\.example\\.com$"]);
+        assert!(domains.contains("preview-42.example.com"));
+        assert!(domains.contains("PREVIEW-42.example.com:80"));
+        assert!(!domains.contains("preview-x.example.com"));
+        assert!(RuleDomains::try_from(vec!["~(".to_string()]).is_err());
+    }
+
+    #[test]
+    fn match_suffix() {
+        let domains = domains(&[".example.com"]);
+        assert!(domains.contains("example.com"));
+        assert!(domains.contains("a.b.example.com"));
+        assert!(!domains.contains("badexample.com"));
+    }
+
+    #[test]
+    fn match_wildcard() {
+        let domains = domains(&["*.example.com"]);
+        assert!(domains.contains("preview-1.example.com:443"));
+        assert!(!domains.contains("example.com"));
+        assert!(!domains.contains("badexample.com"));
+    }
+}
@@ -1,62 +1,306 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 
+use ipnet::IpNet;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::RuleDomains;
+use super::RuleGlob;
+use super::RuleRegex;
 use crate::models::RequestContext;
 
 /// Define attributes a request must match for a rule to be applied.
 ///
-/// All attributes defined must match for a rule to match overall.
+/// For compatibility, requests match if they match ANY of the conditions set directly
+/// (`any: true`, `domain`, `header_absent`, `header_equal`, `header_equal_ignore_case`,
+/// `header_present`, `header_regex`, `method`, `path`, `path_glob`, `path_prefix`, `path_regex`,
+/// `query_param`, `source_cidr`, `uri`, `uri_glob`, `uri_prefix`, `uri_regex`).
+///
+/// Header names are always compared ignoring case.
+/// Use `all`, `any` and `none` lists to combine conditions explicitly:
+/// when combined, ALL the defined parts must match for a rule to match overall.
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct RuleMatches {
-    /// Any requiest will match.
-    #[serde(default = "RuleMatches::default_any")]
-    pub any: bool,
+    /// Requests matching all of these conditions will match.
+    #[serde(default)]
+    pub all: Vec<RuleMatches>,
+
+    /// Any request will match (`true`) or requests matching any of these conditions will match.
+    #[serde(default)]
+    pub any: RuleAny,
 
     /// Requests for any domains in the list will match.
+    ///
+    /// Domains can include wildcards (`*.example.com`), suffixes (`.example.com`)
+    /// and regular expressions prefixed with `~`.
+    #[serde(default)]
+    pub domain: RuleDomains,
+
+    /// Requests missing any of these headers will match.
     #[serde(default)]
-    pub domain: HashSet<String>,
+    pub header_absent: HashSet<String>,
 
     /// Requests with any header set to the corresponding value will match.
     #[serde(default)]
     pub header_equal: HashMap<String, String>,
 
+    /// Requests with any header set to the corresponding value, compared ignoring case, will match.
+    #[serde(default)]
+    pub header_equal_ignore_case: HashMap<String, String>,
+
+    /// Requests with any of these headers will match, regardless of their value.
+    #[serde(default)]
+    pub header_present: HashSet<String>,
+
+    /// Requests with any header value matching the corresponding regular expression will match.
+    #[serde(default)]
+    pub header_regex: HashMap<String, RuleRegex>,
+
+    /// Requests with any of these HTTP methods (compared ignoring case) will match.
+    ///
+    /// Requests match only if the HTTPS proxy provides the original method.
+    #[serde(default)]
+    pub method: HashSet<String>,
+
+    /// Requests matching any of these conditions will NOT match.
+    #[serde(default)]
+    pub none: Vec<RuleMatches>,
+
+    /// Requests matching these conditions will NOT match.
+    #[serde(default)]
+    pub not: Option<Box<RuleMatches>>,
+
+    /// Requests for any path in the list will match.
+    ///
+    /// Paths are percent-decoded and normalised, without the query string.
+    #[serde(default)]
+    pub path: HashSet<String>,
+
+    /// Requests for paths matching any of these glob patterns (like `/api/**`) will match.
+    #[serde(default)]
+    pub path_glob: Vec<RuleGlob>,
+
+    /// Requests for paths starting with any of these prefixes will match.
+    #[serde(default)]
+    pub path_prefix: Vec<String>,
+
+    /// Requests for paths matching any of these regular expressions will match.
+    #[serde(default)]
+    pub path_regex: Vec<RuleRegex>,
+
+    /// Requests with any query string parameter set to the corresponding value will match.
+    #[serde(default)]
+    pub query_param: HashMap<String, String>,
+
+    /// Requests from clients in any of these IP ranges will match.
+    ///
+    /// Requests match only if the client IP address is extracted from the request.
+    #[serde(default)]
+    pub source_cidr: Vec<IpNet>,
+
     /// Requests for any URI in the list will match.
     #[serde(default)]
     pub uri: HashSet<String>,
+
+    /// Requests for URIs matching any of these glob patterns (like `/api/**`) will match.
+    #[serde(default)]
+    pub uri_glob: Vec<RuleGlob>,
+
+    /// Requests for URIs starting with any of these prefixes will match.
+    #[serde(default)]
+    pub uri_prefix: Vec<String>,
+
+    /// Requests for URIs matching any of these regular expressions will match.
+    #[serde(default)]
+    pub uri_regex: Vec<RuleRegex>,
 }
 
 impl RuleMatches {
     /// Check if the context matches this rule.
+    ///
+    /// The conditions set directly on the rule, the `all`, `any` and `none` lists and
+    /// the `not` block must all match, ignoring the ones that are not set.
+    /// Rules with no conditions never match.
     pub fn check(&self, context: &RequestContext) -> bool {
-        self.any
-            || self.domain.contains(context.host)
-            || self.uri.contains(context.uri)
-            || self.check_header_equal(context)
+        let mut parts = Vec::new();
+        if self.has_conditions() {
+            parts.push(
+                self.any == RuleAny::Bool(true)
+                    || self.domain.contains(context.host)
+                    || self.check_uri(context)
+                    || self.check_path(context)
+                    || self.check_query_param(context)
+                    || self.check_headers(context)
+                    || self.check_method(context)
+                    || self.check_source_cidr(context),
+            );
+        }
+        if !self.all.is_empty() {
+            parts.push(self.all.iter().all(|matches| matches.check(context)));
+        }
+        if let RuleAny::Matches(any) = &self.any {
+            if !any.is_empty() {
+                parts.push(any.iter().any(|matches| matches.check(context)));
+            }
+        }
+        if !self.none.is_empty() {
+            parts.push(!self.none.iter().any(|matches| matches.check(context)));
+        }
+        if let Some(not) = &self.not {
+            parts.push(!not.check(context));
+        }
+        !parts.is_empty() && parts.into_iter().all(|part| part)
     }
 }
 
 impl RuleMatches {
-    fn default_any() -> bool {
-        false
+    /// Check if any of the conditions set directly on the rule is set.
+    fn has_conditions(&self) -> bool {
+        self.any == RuleAny::Bool(true)
+            || !self.domain.is_empty()
+            || !self.header_absent.is_empty()
+            || !self.header_equal.is_empty()
+            || !self.header_equal_ignore_case.is_empty()
+            || !self.header_present.is_empty()
+            || !self.header_regex.is_empty()
+            || !self.method.is_empty()
+            || !self.path.is_empty()
+            || !self.path_glob.is_empty()
+            || !self.path_prefix.is_empty()
+            || !self.path_regex.is_empty()
+            || !self.query_param.is_empty()
+            || !self.source_cidr.is_empty()
+            || !self.uri.is_empty()
+            || !self.uri_glob.is_empty()
+            || !self.uri_prefix.is_empty()
+            || !self.uri_regex.is_empty()
+    }
+
+    /// Check if the context method matches any of the listed methods.
+    fn check_method(&self, context: &RequestContext) -> bool {
+        match context.method {
+            None => false,
+            Some(method) => self
+                .method
+                .iter()
+                .any(|expected| expected.eq_ignore_ascii_case(method)),
+        }
     }
 
-    /// Check if the context headers match any of the equality conditions.
-    fn check_header_equal(&self, context: &RequestContext) -> bool {
-        let header_equal = &self.header_equal;
-        context.headers.iter().any(|(name, values)| {
-            header_equal
-                .get(*name)
-                .map(|value| values.contains(&value.as_str()))
+    /// Check if the normalised context path matches any of the path conditions.
+    fn check_path(&self, context: &RequestContext) -> bool {
+        let path = context.path.as_str();
+        self.path.contains(path)
+            || self
+                .path_prefix
+                .iter()
+                .any(|prefix| path.starts_with(prefix.as_str()))
+            || self.path_glob.iter().any(|glob| glob.is_match(path))
+            || self.path_regex.iter().any(|regex| regex.is_match(path))
+    }
+
+    /// Check if the context query parameters match any of the equality conditions.
+    fn check_query_param(&self, context: &RequestContext) -> bool {
+        self.query_param.iter().any(|(name, value)| {
+            context
+                .query
+                .get(name)
+                .map(|values| values.contains(value))
                 .unwrap_or(false)
         })
     }
+
+    /// Check if the client IP address is in any of the listed ranges.
+    fn check_source_cidr(&self, context: &RequestContext) -> bool {
+        match context.client_ip {
+            None => false,
+            Some(client_ip) => self
+                .source_cidr
+                .iter()
+                .any(|cidr| cidr.contains(&client_ip)),
+        }
+    }
+
+    /// Check if the context URI matches any of the URI conditions.
+    ///
+    /// The URI is matched as provided by the HTTPS proxy, including the query string.
+    /// URIs are not normalised so these conditions must not be used for path-based policy,
+    /// in allow or deny rules: `/public/../admin` starts with `/public/` and `/x/../admin`
+    /// does not, but both are served as `/admin` upstream. Use the path conditions instead.
+    fn check_uri(&self, context: &RequestContext) -> bool {
+        self.uri.contains(context.uri)
+            || self
+                .uri_prefix
+                .iter()
+                .any(|prefix| context.uri.starts_with(prefix.as_str()))
+            || self.uri_glob.iter().any(|glob| glob.is_match(context.uri))
+            || self
+                .uri_regex
+                .iter()
+                .any(|regex| regex.is_match(context.uri))
+    }
+
+    /// Check if the context headers match any of the header conditions.
+    fn check_headers(&self, context: &RequestContext) -> bool {
+        let values = |name: &str| header_values(context, name);
+        self.header_present
+            .iter()
+            .any(|name| !values(name).is_empty())
+            || self
+                .header_absent
+                .iter()
+                .any(|name| values(name).is_empty())
+            || self
+                .header_equal
+                .iter()
+                .any(|(name, expected)| values(name).contains(&expected.as_str()))
+            || self
+                .header_equal_ignore_case
+                .iter()
+                .any(|(name, expected)| {
+                    values(name)
+                        .iter()
+                        .any(|value| value.eq_ignore_ascii_case(expected))
+                })
+            || self
+                .header_regex
+                .iter()
+                .any(|(name, regex)| values(name).iter().any(|value| regex.is_match(value)))
+    }
+}
+
+/// Collect the values of a request header, looking up its name ignoring case.
+fn header_values<'context>(context: &'context RequestContext, name: &str) -> Vec<&'context str> {
+    context
+        .headers
+        .iter()
+        .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+        .flat_map(|(_, values)| values.iter().copied())
+        .collect()
+}
+
+/// Match all requests or requests matching any of a list of conditions.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum RuleAny {
+    /// Match all requests when `true`.
+    Bool(bool),
+
+    /// Match requests matching any of these conditions.
+    Matches(Vec<RuleMatches>),
+}
+
+impl Default for RuleAny {
+    fn default() -> RuleAny {
+        RuleAny::Bool(false)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::RuleAny;
+    use super::RuleDomains;
     use super::RuleMatches;
     use crate::models::RequestContext;
     use crate::models::RequestProtocol;
@@ -64,16 +308,21 @@ mod tests {
     #[test]
     fn match_any() {
         let context = RequestContext {
+            client_ip: None,
             headers: Default::default(),
             host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
             protocol: RequestProtocol::Https,
-            uri: &"/path/to/nowhere",
+            query: Default::default(),
+            uri: "/path/to/nowhere",
         };
         let rule = RuleMatches {
-            any: true,
+            any: RuleAny::Bool(true),
             domain: Default::default(),
             header_equal: Default::default(),
             uri: Default::default(),
+            ..Default::default()
         };
         assert!(rule.check(&context));
     }
@@ -81,21 +330,20 @@ mod tests {
     #[test]
     fn match_domain() {
         let context = RequestContext {
+            client_ip: None,
             headers: Default::default(),
             host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
             protocol: RequestProtocol::Https,
-            uri: &"/path/to/nowhere",
+            query: Default::default(),
+            uri: "/path/to/nowhere",
         };
         let rule = RuleMatches {
-            any: false,
-            domain: {
-                let mut set = std::collections::HashSet::new();
-                set.insert("me.not".to_string());
-                set.insert("not.me".to_string());
-                set
-            },
+            domain: RuleDomains::from_static(&["me.not", "not.me"]),
             header_equal: Default::default(),
             uri: Default::default(),
+            ..Default::default()
         };
         assert!(rule.check(&context));
     }
@@ -103,6 +351,7 @@ mod tests {
     #[test]
     fn match_header() {
         let context = RequestContext {
+            client_ip: None,
             headers: {
                 let mut map = std::collections::HashMap::new();
                 map.insert("x-header-check", vec!["no"]);
@@ -110,11 +359,13 @@ mod tests {
                 map
             },
             host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
             protocol: RequestProtocol::Https,
-            uri: &"/path/to/nowhere",
+            query: Default::default(),
+            uri: "/path/to/nowhere",
         };
         let rule = RuleMatches {
-            any: false,
             domain: Default::default(),
             header_equal: {
                 let mut map = std::collections::HashMap::new();
@@ -123,6 +374,7 @@ mod tests {
                 map
             },
             uri: Default::default(),
+            ..Default::default()
         };
         assert!(rule.check(&context));
     }
@@ -130,13 +382,16 @@ mod tests {
     #[test]
     fn match_uri() {
         let context = RequestContext {
+            client_ip: None,
             headers: Default::default(),
             host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
             protocol: RequestProtocol::Https,
-            uri: &"/path/to/nowhere",
+            query: Default::default(),
+            uri: "/path/to/nowhere",
         };
         let rule = RuleMatches {
-            any: false,
             domain: Default::default(),
             header_equal: Default::default(),
             uri: {
@@ -144,7 +399,295 @@ mod tests {
                 set.insert("/path/to/nowhere".to_string());
                 set
             },
+            ..Default::default()
         };
         assert!(rule.check(&context));
     }
+
+    fn not_uri(uri: &str) -> Option<Box<RuleMatches>> {
+        let not = RuleMatches {
+            uri: {
+                let mut set = std::collections::HashSet::new();
+                set.insert(uri.to_string());
+                set
+            },
+            ..Default::default()
+        };
+        Some(Box::new(not))
+    }
+
+    #[test]
+    fn match_not() {
+        let context = RequestContext {
+            client_ip: None,
+            headers: Default::default(),
+            host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
+            protocol: RequestProtocol::Https,
+            query: Default::default(),
+            uri: "/path/to/nowhere",
+        };
+        let rule = RuleMatches {
+            not: not_uri("/admin"),
+            ..Default::default()
+        };
+        assert!(rule.check(&context));
+        let rule = RuleMatches {
+            not: not_uri("/path/to/nowhere"),
+            ..Default::default()
+        };
+        assert!(!rule.check(&context));
+    }
+
+    #[test]
+    fn match_not_with_conditions() {
+        let context = RequestContext {
+            client_ip: None,
+            headers: Default::default(),
+            host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
+            protocol: RequestProtocol::Https,
+            query: Default::default(),
+            uri: "/path/to/nowhere",
+        };
+        let rule = RuleMatches {
+            domain: RuleDomains::from_static(&["me.not"]),
+            not: not_uri("/admin"),
+            ..Default::default()
+        };
+        assert!(!rule.check(&context));
+    }
+
+    #[test]
+    fn never_match_empty() {
+        let context = RequestContext {
+            client_ip: None,
+            headers: Default::default(),
+            host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
+            protocol: RequestProtocol::Https,
+            query: Default::default(),
+            uri: "/path/to/nowhere",
+        };
+        assert!(!RuleMatches::default().check(&context));
+    }
+
+    fn context() -> RequestContext<'static> {
+        RequestContext {
+            client_ip: None,
+            headers: Default::default(),
+            host: "not.me",
+            method: None,
+            path: "/path/to/nowhere".into(),
+            protocol: RequestProtocol::Https,
+            query: Default::default(),
+            uri: "/path/to/nowhere",
+        }
+    }
+
+    fn domain(domain: &str) -> RuleMatches {
+        RuleMatches {
+            domain: RuleDomains::from_static(&[domain]),
+            ..Default::default()
+        }
+    }
+
+    fn uri(uri: &str) -> RuleMatches {
+        *not_uri(uri).unwrap()
+    }
+
+    #[test]
+    fn match_all() {
+        let rule = RuleMatches {
+            all: vec![domain("not.me"), uri("/path/to/nowhere")],
+            ..Default::default()
+        };
+        assert!(rule.check(&context()));
+        let rule = RuleMatches {
+            all: vec![domain("not.me"), uri("/admin")],
+            ..Default::default()
+        };
+        assert!(!rule.check(&context()));
+    }
+
+    #[test]
+    fn match_any_list() {
+        let rule = RuleMatches {
+            any: RuleAny::Matches(vec![domain("me.not"), uri("/path/to/nowhere")]),
+            ..Default::default()
+        };
+        assert!(rule.check(&context()));
+        let rule = RuleMatches {
+            any: RuleAny::Matches(vec![domain("me.not"), uri("/admin")]),
+            ..Default::default()
+        };
+        assert!(!rule.check(&context()));
+    }
+
+    #[test]
+    fn match_combined() {
+        let rule = RuleMatches {
+            domain: RuleDomains::from_static(&["not.me"]),
+            none: vec![uri("/admin"), uri("/path/to/nowhere")],
+            ..Default::default()
+        };
+        assert!(!rule.check(&context()));
+        let rule = RuleMatches {
+            all: vec![domain("not.me")],
+            none: vec![uri("/admin")],
+            ..Default::default()
+        };
+        assert!(rule.check(&context()));
+    }
+
+    #[test]
+    fn parse_any() {
+        let rule: RuleMatches = serde_yaml::from_str("any: true").unwrap();
+        assert_eq!(rule.any, RuleAny::Bool(true));
+        let rule: RuleMatches = serde_yaml::from_str(
+            r#"
+            any:
+              - domain: ['not.me']
+              - uri: ['/admin']
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            rule.any,
+            RuleAny::Matches(vec![domain("not.me"), uri("/admin")])
+        );
+    }
+
+    fn context_with_header(name: &'static str, value: &'static str) -> RequestContext<'static> {
+        let mut context = context();
+        context.headers.insert(name, vec![value]);
+        context
+    }
+
+    #[test]
+    fn match_header_case_insensitive() {
+        let context = context_with_header("user-agent", "Mozilla/5.0");
+        let rule: RuleMatches =
+            serde_yaml::from_str("header_equal: {User-Agent: 'Mozilla/5.0'}").unwrap();
+        assert!(rule.check(&context));
+        let rule: RuleMatches =
+            serde_yaml::from_str("header_equal: {User-Agent: 'mozilla/5.0'}").unwrap();
+        assert!(!rule.check(&context));
+        let rule: RuleMatches =
+            serde_yaml::from_str("header_equal_ignore_case: {User-Agent: 'mozilla/5.0'}").unwrap();
+        assert!(rule.check(&context));
+    }
+
+    #[test]
+    fn match_header_presence() {
+        let with_header = context_with_header("x-request-source", "internal");
+        let rule: RuleMatches =
+            serde_yaml::from_str("header_present: ['X-Request-Source']").unwrap();
+        assert!(rule.check(&with_header));
+        assert!(!rule.check(&context()));
+        let rule: RuleMatches =
+            serde_yaml::from_str("header_absent: ['X-Request-Source']").unwrap();
+        assert!(!rule.check(&with_header));
+        assert!(rule.check(&context()));
+    }
+
+    #[test]
+    fn match_header_regex() {
+        let context = context_with_header("user-agent", "kube-probe/1.27");
+        let rule: RuleMatches =
+            serde_yaml::from_str("header_regex: {User-Agent: '^kube-probe/'}").unwrap();
+        assert!(rule.check(&context));
+        let rule: RuleMatches =
+            serde_yaml::from_str("header_regex: {User-Agent: '^Prometheus/'}").unwrap();
+        assert!(!rule.check(&context));
+        assert!(serde_yaml::from_str::<RuleMatches>("header_regex: {User-Agent: '('}").is_err());
+    }
+
+    #[test]
+    fn match_method() {
+        let rule: RuleMatches = serde_yaml::from_str("method: ['GET', 'head']").unwrap();
+        assert!(!rule.check(&context()));
+        let context = RequestContext {
+            method: Some("HEAD"),
+            ..context()
+        };
+        assert!(rule.check(&context));
+        let context = RequestContext {
+            method: Some("POST"),
+            ..context
+        };
+        assert!(!rule.check(&context));
+    }
+
+    #[test]
+    fn match_path() {
+        let context = RequestContext {
+            path: "/health".into(),
+            uri: "/api/../health?probe=1",
+            ..context()
+        };
+        let rule: RuleMatches = serde_yaml::from_str("path: ['/health']").unwrap();
+        assert!(rule.check(&context));
+        let rule: RuleMatches = serde_yaml::from_str("path_prefix: ['/api/']").unwrap();
+        assert!(!rule.check(&context));
+        let rule: RuleMatches = serde_yaml::from_str("path_glob: ['/*']").unwrap();
+        assert!(rule.check(&context));
+        let rule: RuleMatches = serde_yaml::from_str("path_regex: ['^/health$']").unwrap();
+        assert!(rule.check(&context));
+    }
+
+    #[test]
+    fn match_query_param() {
+        let context = RequestContext {
+            query: {
+                let mut map = std::collections::HashMap::new();
+                map.insert("probe".to_string(), vec!["0".to_string(), "1".to_string()]);
+                map
+            },
+            ..context()
+        };
+        let rule: RuleMatches = serde_yaml::from_str("query_param: {probe: '1'}").unwrap();
+        assert!(rule.check(&context));
+        let rule: RuleMatches = serde_yaml::from_str("query_param: {probe: '2'}").unwrap();
+        assert!(!rule.check(&context));
+    }
+
+    #[test]
+    fn match_source_cidr() {
+        let rule: RuleMatches =
+            serde_yaml::from_str("source_cidr: ['10.0.0.0/8', '2001:db8::/32']").unwrap();
+        assert!(!rule.check(&context()));
+        let context = RequestContext {
+            client_ip: Some("10.20.30.40".parse().unwrap()),
+            ..context()
+        };
+        assert!(rule.check(&context));
+        let context = RequestContext {
+            client_ip: Some("192.0.2.1".parse().unwrap()),
+            ..context
+        };
+        assert!(!rule.check(&context));
+    }
+
+    #[test]
+    fn match_uri_patterns() {
+        let rule: RuleMatches = serde_yaml::from_str("uri_prefix: ['/path/']").unwrap();
+        assert!(rule.check(&context()));
+        let rule: RuleMatches = serde_yaml::from_str("uri_glob: ['/path/**']").unwrap();
+        assert!(rule.check(&context()));
+        let rule: RuleMatches = serde_yaml::from_str("uri_regex: ['^/path/to/[a-z]+$']").unwrap();
+        assert!(rule.check(&context()));
+        let rule: RuleMatches =
+            serde_yaml::from_str("{uri_prefix: ['/admin/'], uri_glob: ['/path/*']}").unwrap();
+        assert!(!rule.check(&context()));
+    }
+
+    #[test]
+    fn reject_invalid_uri_regex() {
+        let rule = serde_yaml::from_str::<RuleMatches>("uri_regex: ['/path/(']");
+        assert!(rule.is_err());
+    }
 }
@@ -7,11 +7,22 @@ use serde::Serialize;
 use crate::models::AuthenticationContext;
 use crate::models::RequestContext;
 
+mod domain;
 mod matches;
+mod pattern;
 mod session_matches;
+mod template;
+mod time_window;
 
+pub use self::domain::RuleDomains;
+#[cfg(test)]
+pub use self::matches::RuleAny;
 pub use self::matches::RuleMatches;
+pub use self::pattern::RuleGlob;
+pub use self::pattern::RuleRegex;
 pub use self::session_matches::RuleSessionMatches;
+pub use self::template::Template;
+pub use self::time_window::RuleTimeWindow;
 
 /// Configure a response customisation rule.
 ///
@@ -25,8 +36,10 @@ pub struct EnrichResponseRule {
     pub headers_remove: HashSet<String>,
 
     /// Set response headers to the provided value, overriding any existing one.
+    ///
+    /// Values are templates that can include information about the authenticated user.
     #[serde(default)]
-    pub headers_set: HashMap<String, String>,
+    pub headers_set: HashMap<String, Template>,
 
     /// Match requests to apply this rule to.
     #[serde(default)]
@@ -35,6 +48,10 @@ pub struct EnrichResponseRule {
     /// Match requests to apply this rule to based on authentication results.
     #[serde(default)]
     pub session_matches: Option<RuleSessionMatches>,
+
+    /// Apply this rule only during a time window.
+    #[serde(default)]
+    pub when: Option<RuleTimeWindow>,
 }
 
 impl EnrichResponseRule {
@@ -51,6 +68,7 @@ impl EnrichResponseRule {
                 .as_ref()
                 .map(|matches| matches.check(auth_context))
                 .unwrap_or(true)
+            && RuleTimeWindow::active(&self.when)
     }
 }
 
@@ -67,6 +85,10 @@ pub struct PostAuthRule {
     /// Match requests to apply this rule to based on authentication results.
     #[serde(default)]
     pub session_matches: Option<RuleSessionMatches>,
+
+    /// Apply this rule only during a time window.
+    #[serde(default)]
+    pub when: Option<RuleTimeWindow>,
 }
 
 impl PostAuthRule {
@@ -83,6 +105,7 @@ impl PostAuthRule {
                 .as_ref()
                 .map(|matches| matches.check(auth_context))
                 .unwrap_or(true)
+            && RuleTimeWindow::active(&self.when)
     }
 }
 
@@ -96,12 +119,16 @@ pub struct PreAuthRule {
 
     /// Match requests to apply this rule to.
     pub matches: RuleMatches,
+
+    /// Apply this rule only during a time window.
+    #[serde(default)]
+    pub when: Option<RuleTimeWindow>,
 }
 
 impl PreAuthRule {
     /// Check if the context matches this rule.
     pub fn check(&self, context: &RequestContext) -> bool {
-        self.matches.check(context)
+        self.matches.check(context) && RuleTimeWindow::active(&self.when)
     }
 }
 
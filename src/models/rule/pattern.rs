@@ -0,0 +1,123 @@
+use std::convert::TryFrom;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Regular expression compiled when rules are loaded.
+///
+/// Expressions are not anchored: use `^` and `$` to match entire values.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct RuleRegex {
+    regex: Regex,
+    source: String,
+}
+
+impl RuleRegex {
+    /// Check if the value matches the expression.
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+impl Eq for RuleRegex {}
+
+impl From<RuleRegex> for String {
+    fn from(regex: RuleRegex) -> String {
+        regex.source
+    }
+}
+
+impl PartialEq for RuleRegex {
+    fn eq(&self, other: &RuleRegex) -> bool {
+        self.source == other.source
+    }
+}
+
+impl TryFrom<String> for RuleRegex {
+    type Error = regex::Error;
+
+    fn try_from(source: String) -> Result<RuleRegex, regex::Error> {
+        let regex = Regex::new(&source)?;
+        Ok(RuleRegex { regex, source })
+    }
+}
+
+/// Glob pattern compiled when rules are loaded.
+///
+/// Patterns match entire values: `*` matches any characters except `/`,
+/// `**` matches any characters including `/` and `?` matches one character except `/`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct RuleGlob(RuleRegex);
+
+impl RuleGlob {
+    /// Check if the value matches the pattern.
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+impl From<RuleGlob> for String {
+    fn from(glob: RuleGlob) -> String {
+        glob.0.source
+    }
+}
+
+impl TryFrom<String> for RuleGlob {
+    type Error = regex::Error;
+
+    fn try_from(source: String) -> Result<RuleGlob, regex::Error> {
+        let mut pattern = String::from("^");
+        let mut chars = source.chars().peekable();
+        while let Some(next) = chars.next() {
+            match next {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    pattern.push_str(".*");
+                }
+                '*' => pattern.push_str("[^/]*"),
+                '?' => pattern.push_str("[^/]"),
+                next => pattern.push_str(&regex::escape(&next.to_string())),
+            }
+        }
+        pattern.push('$');
+        let regex = Regex::new(&pattern)?;
+        Ok(RuleGlob(RuleRegex { regex, source }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::RuleGlob;
+    use super::RuleRegex;
+
+    fn glob(pattern: &str) -> RuleGlob {
+        RuleGlob::try_from(pattern.to_string()).unwrap()
+    }
+
+    #[test]
+    fn glob_matches() {
+        assert!(glob("/api/**").is_match("/api/v1/users"));
+        assert!(glob("/api/*/users").is_match("/api/v1/users"));
+        assert!(glob("/file.?s").is_match("/file.js"));
+        assert!(!glob("/api/*").is_match("/api/v1/users"));
+        assert!(!glob("/api/*").is_match("/other/api/v1"));
+        assert!(!glob("/file.js").is_match("/fileXjs"));
+    }
+
+    #[test]
+    fn invalid_regex() {
+        assert!(RuleRegex::try_from("/api/(".to_string()).is_err());
+    }
+
+    #[test]
+    fn regex_matches() {
+        let regex = RuleRegex::try_from("^/api/v[0-9]+/".to_string()).unwrap();
+        assert!(regex.is_match("/api/v2/users"));
+        assert!(!regex.is_match("/api/latest/users"));
+    }
+}
@@ -7,13 +7,40 @@ use crate::models::AuthenticationContext;
 
 /// Define attributes a request's authentication result must match for a rule to be applied.
 ///
-/// All attributes defined must match for a rule to match overall.
+/// For compatibility, sessions match if they match ANY of the conditions set directly
+/// (`authenticated`, `groups`, `roles`, `user`).
+/// Use `all`, `any` and `none` lists to combine conditions explicitly:
+/// when combined, ALL the defined parts must match for a rule to match overall.
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct RuleSessionMatches {
+    /// Sessions matching all of these conditions will match.
+    #[serde(default)]
+    pub all: Vec<RuleSessionMatches>,
+
+    /// Sessions matching any of these conditions will match.
+    #[serde(default)]
+    pub any: Vec<RuleSessionMatches>,
+
     /// Match requests by authenticator result.
     #[serde(default)]
     pub authenticated: Option<bool>,
 
+    /// Match requests by the groups the user is a member of.
+    #[serde(default)]
+    pub groups: RuleMembershipMatches,
+
+    /// Sessions matching any of these conditions will NOT match.
+    #[serde(default)]
+    pub none: Vec<RuleSessionMatches>,
+
+    /// Requests matching these conditions will NOT match.
+    #[serde(default)]
+    pub not: Option<Box<RuleSessionMatches>>,
+
+    /// Match requests by the roles assigned to the user.
+    #[serde(default)]
+    pub roles: RuleMembershipMatches,
+
     /// Requests originating from any of these users will match.
     #[serde(default)]
     pub user: HashSet<String>,
@@ -21,22 +48,85 @@ pub struct RuleSessionMatches {
 
 impl RuleSessionMatches {
     /// Check if the contexts match this rule.
+    ///
+    /// The conditions set directly on the rule, the `all`, `any` and `none` lists and
+    /// the `not` block must all match, ignoring the ones that are not set.
+    /// Rules with no conditions never match.
     pub fn check(&self, auth_context: &AuthenticationContext) -> bool {
-        let authenticated = self
-            .authenticated
-            .map(|authenticated| authenticated == auth_context.authenticated)
-            .unwrap_or(false);
-        authenticated
-            || auth_context
-                .user
-                .as_ref()
-                .map(|user| self.user.contains(user))
-                .unwrap_or(false)
+        let mut parts = Vec::new();
+        if self.has_conditions() {
+            let authenticated = self
+                .authenticated
+                .map(|authenticated| authenticated == auth_context.authenticated)
+                .unwrap_or(false);
+            parts.push(
+                authenticated
+                    || self.groups.check(&auth_context.groups)
+                    || self.roles.check(&auth_context.roles)
+                    || auth_context
+                        .user
+                        .as_ref()
+                        .map(|user| self.user.contains(user))
+                        .unwrap_or(false),
+            );
+        }
+        if !self.all.is_empty() {
+            parts.push(self.all.iter().all(|matches| matches.check(auth_context)));
+        }
+        if !self.any.is_empty() {
+            parts.push(self.any.iter().any(|matches| matches.check(auth_context)));
+        }
+        if !self.none.is_empty() {
+            parts.push(!self.none.iter().any(|matches| matches.check(auth_context)));
+        }
+        if let Some(not) = &self.not {
+            parts.push(!not.check(auth_context));
+        }
+        !parts.is_empty() && parts.into_iter().all(|part| part)
+    }
+
+    /// Check if any of the conditions set directly on the rule is set.
+    fn has_conditions(&self) -> bool {
+        self.authenticated.is_some()
+            || self.groups.is_set()
+            || self.roles.is_set()
+            || !self.user.is_empty()
+    }
+}
+
+/// Define the memberships (groups or roles) a user must have for a rule to be applied.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RuleMembershipMatches {
+    /// Users must be a member of all of these.
+    #[serde(default)]
+    pub all: HashSet<String>,
+
+    /// Users must be a member of at least one of these.
+    #[serde(default)]
+    pub any: HashSet<String>,
+}
+
+impl RuleMembershipMatches {
+    /// Check if any membership condition is set.
+    pub fn is_set(&self) -> bool {
+        !self.all.is_empty() || !self.any.is_empty()
+    }
+
+    /// Check if the memberships match this rule.
+    ///
+    /// Memberships never match if neither `all` nor `any` are set.
+    pub fn check(&self, memberships: &[String]) -> bool {
+        if !self.is_set() {
+            return false;
+        }
+        let any = self.any.is_empty() || memberships.iter().any(|item| self.any.contains(item));
+        any && self.all.iter().all(|item| memberships.contains(item))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::RuleMembershipMatches;
     use super::RuleSessionMatches;
     use crate::models::AuthenticationContext;
 
@@ -44,12 +134,16 @@ mod tests {
     fn match_authenticated() {
         let context = AuthenticationContext {
             authenticated: true,
+            attributes: Default::default(),
+            groups: Default::default(),
+            roles: Default::default(),
             user: None,
             session: None,
         };
         let rule = RuleSessionMatches {
             authenticated: Some(true),
             user: Default::default(),
+            ..Default::default()
         };
         assert!(rule.check(&context));
     }
@@ -58,12 +152,16 @@ mod tests {
     fn match_unauthenticated() {
         let context = AuthenticationContext {
             authenticated: false,
+            attributes: Default::default(),
+            groups: Default::default(),
+            roles: Default::default(),
             user: None,
             session: None,
         };
         let rule = RuleSessionMatches {
             authenticated: Some(false),
             user: Default::default(),
+            ..Default::default()
         };
         assert!(rule.check(&context));
     }
@@ -72,6 +170,9 @@ mod tests {
     fn match_user() {
         let context = AuthenticationContext {
             authenticated: true,
+            attributes: Default::default(),
+            groups: Default::default(),
+            roles: Default::default(),
             user: Some("email@dev.local".to_string()),
             session: None,
         };
@@ -83,6 +184,7 @@ mod tests {
                 set.insert("email@dev.local".to_string());
                 set
             },
+            ..Default::default()
         };
         assert!(rule.check(&context));
     }
@@ -91,16 +193,23 @@ mod tests {
     fn never_match_authenticated_not_set() {
         let context = AuthenticationContext {
             authenticated: true,
+            attributes: Default::default(),
+            groups: Default::default(),
+            roles: Default::default(),
             user: None,
             session: None,
         };
         let rule = RuleSessionMatches {
             authenticated: None,
             user: Default::default(),
+            ..Default::default()
         };
         assert!(!rule.check(&context));
         let context = AuthenticationContext {
             authenticated: false,
+            attributes: Default::default(),
+            groups: Default::default(),
+            roles: Default::default(),
             user: None,
             session: None,
         };
@@ -111,12 +220,140 @@ mod tests {
     fn never_match_user_not_set() {
         let context = AuthenticationContext {
             authenticated: true,
+            attributes: Default::default(),
+            groups: Default::default(),
+            roles: Default::default(),
+            user: Some("email@dev.local".to_string()),
+            session: None,
+        };
+        let rule = RuleSessionMatches {
+            authenticated: None,
+            user: Default::default(),
+            ..Default::default()
+        };
+        assert!(!rule.check(&context));
+    }
+
+    fn membership(all: &[&str], any: &[&str]) -> RuleMembershipMatches {
+        RuleMembershipMatches {
+            all: all.iter().map(|item| item.to_string()).collect(),
+            any: any.iter().map(|item| item.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn match_groups_all() {
+        let groups = vec!["dev".to_string(), "sre".to_string()];
+        assert!(membership(&["dev", "sre"], &[]).check(&groups));
+        assert!(!membership(&["dev", "admins"], &[]).check(&groups));
+    }
+
+    #[test]
+    fn match_groups_any() {
+        let groups = vec!["dev".to_string()];
+        assert!(membership(&[], &["dev", "sre"]).check(&groups));
+        assert!(!membership(&[], &["admins", "sre"]).check(&groups));
+        assert!(!membership(&["dev"], &["admins"]).check(&groups));
+    }
+
+    #[test]
+    fn match_roles() {
+        let context = AuthenticationContext {
+            authenticated: true,
+            attributes: Default::default(),
+            groups: vec!["sre".to_string()],
+            roles: vec!["admin".to_string()],
             user: Some("email@dev.local".to_string()),
             session: None,
         };
         let rule = RuleSessionMatches {
             authenticated: None,
+            roles: membership(&[], &["admin"]),
+            user: Default::default(),
+            ..Default::default()
+        };
+        assert!(rule.check(&context));
+        let rule = RuleSessionMatches {
+            authenticated: None,
+            roles: membership(&[], &["sre"]),
             user: Default::default(),
+            ..Default::default()
+        };
+        assert!(!rule.check(&context));
+    }
+
+    #[test]
+    fn never_match_membership_not_set() {
+        let groups = vec!["dev".to_string()];
+        assert!(!RuleMembershipMatches::default().check(&groups));
+    }
+
+    #[test]
+    fn match_not_user() {
+        let context = AuthenticationContext {
+            authenticated: true,
+            attributes: Default::default(),
+            groups: Default::default(),
+            roles: Default::default(),
+            user: Some("email@dev.local".to_string()),
+            session: None,
+        };
+        let not = RuleSessionMatches {
+            user: {
+                let mut set = std::collections::HashSet::new();
+                set.insert("email@dev.local".to_string());
+                set
+            },
+            ..Default::default()
+        };
+        let rule = RuleSessionMatches {
+            authenticated: Some(true),
+            not: Some(Box::new(not.clone())),
+            ..Default::default()
+        };
+        assert!(!rule.check(&context));
+        let rule = RuleSessionMatches {
+            not: Some(Box::new(not)),
+            ..Default::default()
+        };
+        assert!(!rule.check(&context));
+        let context = AuthenticationContext {
+            user: Some("other@dev.local".to_string()),
+            ..context
+        };
+        assert!(rule.check(&context));
+    }
+
+    #[test]
+    fn match_all() {
+        let context = AuthenticationContext {
+            authenticated: true,
+            attributes: Default::default(),
+            groups: vec!["sre".to_string()],
+            roles: Default::default(),
+            user: Some("email@dev.local".to_string()),
+            session: None,
+        };
+        let sre = RuleSessionMatches {
+            groups: membership(&[], &["sre"]),
+            ..Default::default()
+        };
+        let authenticated = RuleSessionMatches {
+            authenticated: Some(true),
+            ..Default::default()
+        };
+        let rule = RuleSessionMatches {
+            all: vec![authenticated.clone(), sre],
+            ..Default::default()
+        };
+        assert!(rule.check(&context));
+        let admin = RuleSessionMatches {
+            roles: membership(&[], &["admin"]),
+            ..Default::default()
+        };
+        let rule = RuleSessionMatches {
+            all: vec![authenticated, admin],
+            ..Default::default()
         };
         assert!(!rule.check(&context));
     }
@@ -0,0 +1,219 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::models::AuthenticationContext;
+
+/// Authentication context value a template expression renders.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TemplateValue {
+    /// An authentication attribute, empty if not set.
+    Attribute(String),
+
+    /// The groups of the user, joined with the given separator.
+    Groups(String),
+
+    /// The roles of the user, joined with the given separator.
+    Roles(String),
+
+    /// The session ID, empty if not set.
+    Session,
+
+    /// The user ID, empty if not set.
+    User,
+}
+
+impl TemplateValue {
+    /// Parse a `name` or `name | join("separator")` template expression.
+    fn parse(expression: &str) -> Result<TemplateValue> {
+        let (name, filter) = match expression.split_once('|') {
+            None => (expression.trim(), None),
+            Some((name, filter)) => (name.trim(), Some(filter.trim())),
+        };
+        let separator = match filter {
+            None => None,
+            Some(filter) => Some(TemplateValue::parse_join(filter)?),
+        };
+        let value = match (name, separator) {
+            ("groups", separator) => TemplateValue::Groups(separator.unwrap_or_else(|| ",".into())),
+            ("roles", separator) => TemplateValue::Roles(separator.unwrap_or_else(|| ",".into())),
+            (_, Some(_)) => anyhow::bail!("only `groups` and `roles` can be joined"),
+            ("email", None) => TemplateValue::Attribute("email".into()),
+            ("session", None) => TemplateValue::Session,
+            ("user", None) => TemplateValue::User,
+            (name, None) => match name.strip_prefix("attributes.") {
+                Some(attribute) if !attribute.is_empty() => {
+                    TemplateValue::Attribute(attribute.into())
+                }
+                _ => anyhow::bail!("unknown template value `{}`", name),
+            },
+        };
+        Ok(value)
+    }
+
+    /// Parse the separator from a `join("separator")` filter.
+    fn parse_join(filter: &str) -> Result<String> {
+        let separator = filter
+            .strip_prefix("join(")
+            .and_then(|filter| filter.strip_suffix(')'))
+            .map(|separator| separator.trim())
+            .ok_or_else(|| anyhow::anyhow!("unknown template filter `{}`", filter))?;
+        let quoted = separator.len() >= 2
+            && (separator.starts_with('"') && separator.ends_with('"')
+                || separator.starts_with('\'') && separator.ends_with('\''));
+        if !quoted {
+            anyhow::bail!("the `join` separator must be a quoted string");
+        }
+        Ok(separator[1..separator.len() - 1].to_string())
+    }
+
+    fn render(&self, auth_context: &AuthenticationContext) -> String {
+        match self {
+            TemplateValue::Attribute(name) => auth_context
+                .attributes
+                .get(name)
+                .cloned()
+                .unwrap_or_default(),
+            TemplateValue::Groups(separator) => auth_context.groups.join(separator),
+            TemplateValue::Roles(separator) => auth_context.roles.join(separator),
+            TemplateValue::Session => auth_context.session.clone().unwrap_or_default(),
+            TemplateValue::User => auth_context.user.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Part of a parsed template.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Value(TemplateValue),
+}
+
+/// A string with `{{ value }}` expressions replaced by authentication context values.
+///
+/// Supported values are `user`, `session`, `email`, `attributes.<name>`, `groups` and `roles`.
+/// Groups and roles are joined with `,` unless a separator is given with `join("separator")`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+    source: String,
+}
+
+impl Template {
+    /// Render the template with values from the authentication context.
+    pub fn render(&self, auth_context: &AuthenticationContext) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(literal) => literal.clone(),
+                TemplatePart::Value(value) => value.render(auth_context),
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Template> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| anyhow::anyhow!("unclosed `{{{{` in template `{}`", source))?;
+            let expression = &rest[start + 2..start + end];
+            let value = TemplateValue::parse(expression)
+                .map_err(|error| anyhow::anyhow!("{} in template `{}`", error, source))?;
+            parts.push(TemplatePart::Value(value));
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+        Ok(Template {
+            parts,
+            source: source.to_string(),
+        })
+    }
+}
+
+impl From<Template> for String {
+    fn from(template: Template) -> String {
+        template.source
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Template> {
+        source.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Template;
+    use crate::models::AuthenticationContext;
+
+    fn context() -> AuthenticationContext {
+        AuthenticationContext {
+            authenticated: true,
+            attributes: {
+                let mut map = std::collections::HashMap::new();
+                map.insert("email".to_string(), "alice@example.com".to_string());
+                map
+            },
+            groups: vec!["dev".to_string()],
+            roles: vec!["admin".to_string(), "sre".to_string()],
+            user: Some("alice".to_string()),
+            session: Some("abc".to_string()),
+        }
+    }
+
+    fn render(template: &str) -> String {
+        template.parse::<Template>().unwrap().render(&context())
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!("{{user".parse::<Template>().is_err());
+        assert!("{{password}}".parse::<Template>().is_err());
+        assert!("{{user | join(\",\")}}".parse::<Template>().is_err());
+        assert!("{{roles | upper}}".parse::<Template>().is_err());
+        assert!("{{roles | join(,)}}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn render_lists() {
+        assert_eq!(render("{{ roles }}"), "admin,sre");
+        assert_eq!(render("{{roles | join(\" \")}}"), "admin sre");
+        assert_eq!(render("{{groups|join(';')}}"), "dev");
+    }
+
+    #[test]
+    fn render_missing_values() {
+        let template: Template = "<{{user}}|{{attributes.team}}>".parse().unwrap();
+        let context = AuthenticationContext::unauthenticated();
+        assert_eq!(template.render(&context), "<|>");
+    }
+
+    #[test]
+    fn render_static() {
+        assert_eq!(render("static value"), "static value");
+    }
+
+    #[test]
+    fn render_values() {
+        let rendered = render("{{user}} <{{email}}> {{ session }}");
+        assert_eq!(rendered, "alice <alice@example.com> abc");
+    }
+}
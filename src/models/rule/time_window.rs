@@ -0,0 +1,201 @@
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::NaiveTime;
+use chrono::Utc;
+use chrono::Weekday;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Range of hours in the day, formatted as `HH:MM-HH:MM`.
+///
+/// The start time is included and the end time is excluded.
+/// Ranges ending before they start span midnight (`22:00-06:00`).
+/// Weekdays are checked at the time of the request, not on the day the range started,
+/// so on the day after a listed weekday the early hours of an overnight range don't apply.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct RuleHours {
+    end: NaiveTime,
+    source: String,
+    start: NaiveTime,
+}
+
+impl RuleHours {
+    /// Check if the time of day is in the range.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+impl From<RuleHours> for String {
+    fn from(hours: RuleHours) -> String {
+        hours.source
+    }
+}
+
+impl TryFrom<String> for RuleHours {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<RuleHours> {
+        let (start, end) = source.split_once('-').ok_or_else(|| {
+            anyhow::anyhow!("hours `{}` must be formatted as HH:MM-HH:MM", source)
+        })?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|error| anyhow::anyhow!("invalid time `{}` in hours: {}", time, error))
+        };
+        let start = parse(start)?;
+        let end = parse(end)?;
+        if start == end {
+            anyhow::bail!("hours `{}` must not start and end at the same time", source);
+        }
+        Ok(RuleHours { end, source, start })
+    }
+}
+
+/// Limit when rules apply, in the configured timezone.
+///
+/// Rules apply only if ALL the conditions set are met.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RuleTimeWindow {
+    /// Apply rules only during any of these ranges of hours.
+    #[serde(default)]
+    pub hours: Vec<RuleHours>,
+
+    /// Apply rules only until this time (RFC 3339 timestamp, like `2022-03-31T18:00:00Z`).
+    #[serde(default)]
+    pub not_after: Option<DateTime<Utc>>,
+
+    /// Apply rules only from this time (RFC 3339 timestamp, like `2022-03-01T09:00:00Z`).
+    #[serde(default)]
+    pub not_before: Option<DateTime<Utc>>,
+
+    /// Timezone to check hours and weekdays in (like `Europe/London`).
+    #[serde(default)]
+    pub timezone: Tz,
+
+    /// Apply rules only on any of these days of the week (like `mon` or `monday`).
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+}
+
+impl RuleTimeWindow {
+    /// Check if a rule limited to the optional window applies now.
+    ///
+    /// Rules without a window always apply.
+    pub fn active(window: &Option<RuleTimeWindow>) -> bool {
+        window
+            .as_ref()
+            .map(|window| window.contains_now())
+            .unwrap_or(true)
+    }
+
+    /// Check if the rule applies at the given time.
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        if self.not_before.map(|start| now < start).unwrap_or(false) {
+            return false;
+        }
+        if self.not_after.map(|end| now > end).unwrap_or(false) {
+            return false;
+        }
+        let local = now.with_timezone(&self.timezone);
+        if !self.weekdays.is_empty() && !self.weekdays.contains(&local.weekday()) {
+            return false;
+        }
+        self.hours.is_empty() || self.hours.iter().any(|hours| hours.contains(local.time()))
+    }
+
+    /// Check if the rule applies now.
+    pub fn contains_now(&self) -> bool {
+        self.contains(Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use chrono::Utc;
+
+    use super::RuleTimeWindow;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        timestamp.parse().unwrap()
+    }
+
+    fn window(yaml: &str) -> RuleTimeWindow {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn absolute_dates() {
+        let window = window(
+            r#"
+            not_before: '2022-03-01T09:00:00Z'
+            not_after: '2022-03-31T18:00:00+01:00'
+            "#,
+        );
+        assert!(!window.contains(at("2022-03-01T08:59:59Z")));
+        assert!(window.contains(at("2022-03-01T09:00:00Z")));
+        assert!(window.contains(at("2022-03-31T17:00:00Z")));
+        assert!(!window.contains(at("2022-03-31T17:00:01Z")));
+    }
+
+    #[test]
+    fn empty_window_always_applies() {
+        assert!(RuleTimeWindow::default().contains(at("2022-03-01T09:00:00Z")));
+    }
+
+    #[test]
+    fn invalid_hours() {
+        let invalid = |hours: &str| {
+            serde_yaml::from_str::<RuleTimeWindow>(&format!("hours: ['{}']", hours)).is_err()
+        };
+        assert!(invalid("09:00"));
+        assert!(invalid("09:00-25:00"));
+        assert!(invalid("09:00-09:00"));
+        assert!(serde_yaml::from_str::<RuleTimeWindow>("timezone: Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn overnight_hours() {
+        let window = window("hours: ['22:00-06:00']");
+        assert!(window.contains(at("2022-03-01T23:30:00Z")));
+        assert!(window.contains(at("2022-03-02T05:59:00Z")));
+        assert!(!window.contains(at("2022-03-02T06:00:00Z")));
+    }
+
+    #[test]
+    fn overnight_hours_on_request_weekday() {
+        let window = window("{hours: ['22:00-06:00'], weekdays: [fri]}");
+        // Friday early morning and late night.
+        assert!(window.contains(at("2022-03-04T02:00:00Z")));
+        assert!(window.contains(at("2022-03-04T23:00:00Z")));
+        // Saturday early morning, in the range started on Friday.
+        assert!(!window.contains(at("2022-03-05T02:00:00Z")));
+    }
+
+    #[test]
+    fn weekdays_and_hours_in_timezone() {
+        let window = window(
+            r#"
+            hours: ['09:00-18:00']
+            timezone: America/New_York
+            weekdays: [mon, tue, wed, thu, fri]
+            "#,
+        );
+        // Tuesday 10:00 in New York.
+        assert!(window.contains(at("2022-03-01T15:00:00Z")));
+        // Tuesday 08:00 in New York, 13:00 UTC.
+        assert!(!window.contains(at("2022-03-01T13:00:00Z")));
+        // Saturday 10:00 in New York.
+        assert!(!window.contains(at("2022-03-05T15:00:00Z")));
+    }
+}
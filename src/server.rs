@@ -55,8 +55,9 @@ async fn check(
     for (header, value) in result.headers.iter() {
         response.append_header((header, value.to_owned()));
     }
-    if let Some(user) = result.authentication_context.user {
-        response.append_header((&authenticator.headers.user_id, user));
+    if let Some(user) = &result.authentication_context.user {
+        let headers = authenticator.identity_headers(&result);
+        response.append_header((headers.user_id, user.clone()));
     }
 
     // Send audit record to configured auditor.
@@ -134,9 +135,9 @@ mod tests {
 
     #[actix_rt::test]
     async fn bad_request_without_host() {
-        let mut app = test_app().await;
+        let app = test_app().await;
         let request = test::TestRequest::get().uri("/v1/check").to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         let body = test::read_body(response).await;
         assert_eq!(body, Bytes::from_static(b"Required Host header is missing"));
@@ -144,12 +145,12 @@ mod tests {
 
     #[actix_rt::test]
     async fn bad_request_without_protocol() {
-        let mut app = test_app().await;
+        let app = test_app().await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         let body = test::read_body(response).await;
         assert_eq!(
@@ -160,13 +161,13 @@ mod tests {
 
     #[actix_rt::test]
     async fn bad_request_without_uri() {
-        let mut app = test_app().await;
+        let app = test_app().await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .append_header(("X-Forwarded-Proto", "https"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         let body = test::read_body(response).await;
         assert_eq!(
@@ -177,14 +178,14 @@ mod tests {
 
     #[actix_rt::test]
     async fn check_allowed() {
-        let mut app = test_app().await;
+        let app = test_app().await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .append_header(("X-Forwarded-Proto", "https"))
             .append_header(("X-Original-URI", "/"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::OK);
         let body = test::read_body(response).await;
         assert_eq!(body, Bytes::from_static(b""));
@@ -192,14 +193,14 @@ mod tests {
 
     #[actix_rt::test]
     async fn check_appends_headers() {
-        let mut app = test_app().await;
+        let app = test_app().await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .append_header(("X-Forwarded-Proto", "https"))
             .append_header(("X-Original-URI", "/"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         let mut actual: Vec<(String, String)> = response
             .headers()
             .iter()
@@ -217,14 +218,14 @@ mod tests {
     #[actix_rt::test]
     async fn check_denied() {
         let auth = crate::authenticator::tests::Authenticator::denied();
-        let mut app = test_app_with_authenticator(auth).await;
+        let app = test_app_with_authenticator(auth).await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .append_header(("X-Forwarded-Proto", "https"))
             .append_header(("X-Original-URI", "/"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::FORBIDDEN);
         let body = test::read_body(response).await;
         assert_eq!(body, Bytes::from_static(b""));
@@ -233,18 +234,22 @@ mod tests {
     #[actix_rt::test]
     async fn check_extractor_config() {
         let extraction = RequestExtraction {
+            client_ip: "Not-Default-Client-IP".into(),
+            forwarded_for: "Not-Default-Forwarded-For".into(),
             host: "Not-Default-Host".into(),
+            method: "Not-Default-Method".into(),
             protocol: "Not-Default-Proto".into(),
+            trusted_proxies: None,
             uri: "Not-Default-URI".into(),
         };
-        let mut app = test_app_with_extraction(extraction).await;
+        let app = test_app_with_extraction(extraction).await;
         let request = test::TestRequest::get()
             .append_header(("Not-Default-Host", "domain.example.com"))
             .append_header(("Not-Default-Proto", "https"))
             .append_header(("Not-Default-URI", "/"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::OK);
         let body = test::read_body(response).await;
         assert_eq!(body, Bytes::from_static(b""));
@@ -253,14 +258,14 @@ mod tests {
     #[actix_rt::test]
     async fn check_fails() {
         let auth = crate::authenticator::tests::Authenticator::failing();
-        let mut app = test_app_with_authenticator(auth).await;
+        let app = test_app_with_authenticator(auth).await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .append_header(("X-Forwarded-Proto", "https"))
             .append_header(("X-Original-URI", "/"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
         let body = test::read_body(response).await;
         assert_eq!(
@@ -278,14 +283,14 @@ mod tests {
     #[actix_rt::test]
     async fn check_identity_headers_user_id() {
         let auth = crate::authenticator::tests::Authenticator::alice();
-        let mut app = test_app_with_authenticator(auth).await;
+        let app = test_app_with_authenticator(auth).await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .append_header(("X-Forwarded-Proto", "https"))
             .append_header(("X-Original-URI", "/"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::OK);
         let user_id = response
             .headers()
@@ -297,14 +302,14 @@ mod tests {
     #[actix_rt::test]
     async fn check_must_login() {
         let auth = crate::authenticator::tests::Authenticator::must_login();
-        let mut app = test_app_with_authenticator(auth).await;
+        let app = test_app_with_authenticator(auth).await;
         let request = test::TestRequest::get()
             .append_header(("Host", "domain.example.com"))
             .append_header(("X-Forwarded-Proto", "https"))
             .append_header(("X-Original-URI", "/"))
             .uri("/v1/check")
             .to_request();
-        let response = test::call_service(&mut app, request).await;
+        let response = test::call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
         let body = test::read_body(response).await;
         assert_eq!(body, Bytes::from_static(b""));